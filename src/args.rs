@@ -8,6 +8,11 @@ use structopt::{clap, clap::arg_enum, StructOpt};
 pub struct Opt {
     #[structopt(long = "log-level", possible_values(&LogLevel::variants()))]
     pub log_level: Option<LogLevel>,
+    #[structopt(
+        long = "config",
+        about = "path to config yaml of languages, tokenizers and site metadata"
+    )]
+    pub config: Option<PathBuf>,
     #[structopt(subcommand)]
     pub subcommand: SubCommands,
 }
@@ -41,6 +46,17 @@ pub enum SubCommands {
         skip_update_date: bool,
         #[structopt(long = "rebuild")]
         rebuild: bool,
+        #[structopt(
+            long = "watch",
+            about = "Keep watching input and reindex changed markdown"
+        )]
+        watch: bool,
+        #[structopt(
+            long = "watch-interval",
+            default_value = "1000",
+            about = "Polling interval of watch mode in milliseconds"
+        )]
+        watch_interval: u64,
     },
 
     #[structopt(name = "run", about = "run server")]
@@ -56,6 +72,13 @@ pub enum SubCommands {
         _cors_origin: Option<String>,
         #[structopt(long = "static-dir", aliases = &["static", "public", "public-dir"])]
         static_dir: PathBuf,
+        #[structopt(long = "preview", about = "Serve drafts and scheduled posts")]
+        preview: bool,
+        #[structopt(
+            long = "preview-token",
+            about = "Serve drafts and scheduled posts to the requests with this token"
+        )]
+        preview_token: Option<String>,
     },
 
     #[structopt(name = "template", about = "stdout markdown template")]
@@ -103,4 +126,19 @@ pub enum SubCommands {
         #[structopt(long = "index-dir", about = "path to tanitivy index directory")]
         index_dir: PathBuf,
     },
+    #[structopt(
+        name = "sitemap",
+        about = "write sitemap.xml of the indexed posts for static hosting"
+    )]
+    #[structopt(setting(clap::AppSettings::ColoredHelp))]
+    Sitemap {
+        #[structopt(long = "index-dir", about = "path to tanitivy index directory")]
+        index_dir: PathBuf,
+        #[structopt(
+            short = "-o",
+            long = "output",
+            about = "path to the output file. stdout if not specified"
+        )]
+        output: Option<PathBuf>,
+    },
 }
@@ -0,0 +1,520 @@
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, Context, Result};
+use tantivy::tokenizer::Language;
+use yaml_rust::{Yaml, YamlLoader};
+
+use crate::io::read_string;
+use crate::posts::Lang;
+
+/// Tokenizer of the language specific fields.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenizerKind {
+    /// Japanese morphological analysis by lindera
+    Lindera,
+    /// lowercasing and stemming in the language. English stop words are also removed.
+    Stemmer(Language),
+    /// unigrams and bigrams of characters, for the languages without spaces between words
+    Ngram,
+    /// split by whitespaces and punctuations with lowercasing
+    Simple,
+}
+
+impl FromStr for TokenizerKind {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        let kind = match name.to_lowercase().as_str() {
+            "lindera" => TokenizerKind::Lindera,
+            "ngram" => TokenizerKind::Ngram,
+            "simple" => TokenizerKind::Simple,
+            "arabic" => TokenizerKind::Stemmer(Language::Arabic),
+            "danish" => TokenizerKind::Stemmer(Language::Danish),
+            "dutch" => TokenizerKind::Stemmer(Language::Dutch),
+            "english" => TokenizerKind::Stemmer(Language::English),
+            "finnish" => TokenizerKind::Stemmer(Language::Finnish),
+            "french" => TokenizerKind::Stemmer(Language::French),
+            "german" => TokenizerKind::Stemmer(Language::German),
+            "greek" => TokenizerKind::Stemmer(Language::Greek),
+            "hungarian" => TokenizerKind::Stemmer(Language::Hungarian),
+            "italian" => TokenizerKind::Stemmer(Language::Italian),
+            "norwegian" => TokenizerKind::Stemmer(Language::Norwegian),
+            "portuguese" => TokenizerKind::Stemmer(Language::Portuguese),
+            "romanian" => TokenizerKind::Stemmer(Language::Romanian),
+            "russian" => TokenizerKind::Stemmer(Language::Russian),
+            "spanish" => TokenizerKind::Stemmer(Language::Spanish),
+            "swedish" => TokenizerKind::Stemmer(Language::Swedish),
+            "tamil" => TokenizerKind::Stemmer(Language::Tamil),
+            "turkish" => TokenizerKind::Stemmer(Language::Turkish),
+            _ => return Err(anyhow!("{} is not supported tokenizer", name)),
+        };
+        Ok(kind)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LangConfig {
+    /// canonical BCP-47 tag such as `ja` or `zh-Hant`
+    pub tag: String,
+    pub tokenizer: TokenizerKind,
+}
+
+/// Metadata of the site used in feeds and sitemaps
+#[derive(Debug, Clone, PartialEq)]
+pub struct SiteConfig {
+    pub title: String,
+    pub description: String,
+    /// base url without the trailing slash, such as `https://example.com`
+    pub base_url: String,
+    pub author: Option<String>,
+    /// template of post urls with `{base}`, `{lang}`, `{slug}`, `{uuid}` and `{category}`
+    pub post_url: String,
+}
+
+impl Default for SiteConfig {
+    fn default() -> Self {
+        Self {
+            title: "smark".to_string(),
+            description: String::new(),
+            base_url: "http://localhost:8080".to_string(),
+            author: None,
+            post_url: "{base}/{lang}/posts/{slug}".to_string(),
+        }
+    }
+}
+
+impl SiteConfig {
+    fn from_yaml(yaml: &Yaml) -> Result<Self> {
+        let default = Self::default();
+        let hash = match yaml {
+            Yaml::BadValue => return Ok(default),
+            Yaml::Hash(hash) => hash,
+            _ => return Err(anyhow!("site must be a map")),
+        };
+        for key in hash.keys() {
+            match key.as_str() {
+                Some("title" | "description" | "base_url" | "author" | "post_url") => (),
+                _ => return Err(anyhow!("{:?} is not a key of site", key)),
+            }
+        }
+
+        let get = |key: &str| -> Result<Option<String>> {
+            match &yaml[key] {
+                Yaml::BadValue => Ok(None),
+                Yaml::String(value) => Ok(Some(value.to_string())),
+                _ => Err(anyhow!("site.{} must be string", key)),
+            }
+        };
+        Ok(Self {
+            title: get("title")?.unwrap_or(default.title),
+            description: get("description")?.unwrap_or(default.description),
+            base_url: get("base_url")?
+                .map(|url| url.trim_end_matches('/').to_string())
+                .unwrap_or(default.base_url),
+            author: get("author")?,
+            post_url: get("post_url")?.unwrap_or(default.post_url),
+        })
+    }
+
+    /// Url of the post by `post_url` template
+    pub fn post_url(&self, lang: &str, slug: &str, uuid: &str, category: &str) -> String {
+        self.post_url
+            .replace("{base}", &self.base_url)
+            .replace("{lang}", lang)
+            .replace("{slug}", slug)
+            .replace("{uuid}", uuid)
+            .replace("{category}", category)
+    }
+}
+
+/// Type of a frontmatter value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValueKind {
+    String,
+    Integer,
+    /// integer or real number
+    Number,
+    Bool,
+    List,
+    Map,
+    /// string in one of the formats of `created_at`
+    Date,
+}
+
+impl FromStr for ValueKind {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        let kind = match name {
+            "string" => ValueKind::String,
+            "integer" => ValueKind::Integer,
+            "number" => ValueKind::Number,
+            "bool" => ValueKind::Bool,
+            "list" => ValueKind::List,
+            "map" => ValueKind::Map,
+            "date" => ValueKind::Date,
+            _ => return Err(anyhow!("{} is not a type of frontmatter value", name)),
+        };
+        Ok(kind)
+    }
+}
+
+impl ValueKind {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ValueKind::String => "string",
+            ValueKind::Integer => "integer",
+            ValueKind::Number => "number",
+            ValueKind::Bool => "bool",
+            ValueKind::List => "list",
+            ValueKind::Map => "map",
+            ValueKind::Date => "date",
+        }
+    }
+}
+
+/// Rule of a frontmatter key
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FieldRule {
+    pub key: String,
+    pub kind: Option<ValueKind>,
+    pub required: bool,
+    /// allowed values of the key, or of each item of the list. Any value is allowed if empty.
+    pub values: Vec<String>,
+    /// maximum number of characters of the string
+    pub max_length: Option<usize>,
+}
+
+impl FieldRule {
+    fn from_yaml(key: &str, yaml: &Yaml) -> Result<Self> {
+        let hash = match yaml {
+            Yaml::Hash(hash) => hash,
+            _ => return Err(anyhow!("frontmatter.fields.{} must be a map", key)),
+        };
+        let mut rule = Self {
+            key: key.to_string(),
+            ..Self::default()
+        };
+        for (name, value) in hash.iter() {
+            match (name.as_str(), value) {
+                (Some("type"), Yaml::String(kind)) => rule.kind = Some(kind.parse()?),
+                (Some("required"), Yaml::Boolean(required)) => rule.required = *required,
+                (Some("enum"), Yaml::Array(values)) => {
+                    rule.values = values
+                        .iter()
+                        .map(|value| match value {
+                            Yaml::String(s) => Ok(s.to_owned()),
+                            Yaml::Integer(i) => Ok(i.to_string()),
+                            _ => Err(anyhow!("frontmatter.fields.{}.enum must be strings", key)),
+                        })
+                        .collect::<Result<_>>()?
+                }
+                (Some("max_length"), Yaml::Integer(max)) if *max >= 0 => {
+                    rule.max_length = Some(*max as usize)
+                }
+                _ => {
+                    return Err(anyhow!(
+                        "{:?} is invalid in frontmatter.fields.{}",
+                        name,
+                        key
+                    ))
+                }
+            }
+        }
+        Ok(rule)
+    }
+}
+
+/// Rules of frontmatter checked at prep
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FrontMatterConfig {
+    /// prep fails with violations of the rules if true, otherwise they are warned
+    pub strict: bool,
+    pub fields: Vec<FieldRule>,
+}
+
+impl FrontMatterConfig {
+    fn from_yaml(yaml: &Yaml) -> Result<Self> {
+        let hash = match yaml {
+            Yaml::BadValue => return Ok(Self::default()),
+            Yaml::Hash(hash) => hash,
+            _ => return Err(anyhow!("frontmatter must be a map")),
+        };
+        for key in hash.keys() {
+            match key.as_str() {
+                Some("strict" | "fields") => (),
+                _ => return Err(anyhow!("{:?} is not a key of frontmatter", key)),
+            }
+        }
+
+        let strict = match &yaml["strict"] {
+            Yaml::BadValue => false,
+            Yaml::Boolean(strict) => *strict,
+            _ => return Err(anyhow!("frontmatter.strict must be true or false")),
+        };
+        let fields = match &yaml["fields"] {
+            Yaml::BadValue => Vec::new(),
+            Yaml::Hash(hash) => hash
+                .iter()
+                .map(|(key, rule)| match key.as_str() {
+                    Some(key) => FieldRule::from_yaml(key, rule),
+                    None => Err(anyhow!("keys of frontmatter.fields must be string")),
+                })
+                .collect::<Result<_>>()?,
+            _ => return Err(anyhow!("frontmatter.fields must be a map")),
+        };
+        Ok(Self { strict, fields })
+    }
+}
+
+/// Configuration given by `--config`.
+///
+/// ```yaml
+/// default_lang: ja
+/// languages:
+///   ja: lindera
+///   en: english
+///   ko: ngram
+/// site:
+///   title: My Blog
+///   description: Notes on programming
+///   base_url: https://example.com
+///   author: smark
+///   post_url: "{base}/{lang}/posts/{slug}"
+/// frontmatter:
+///   strict: true
+///   fields:
+///     category:
+///       enum: [programming, life]
+///     title:
+///       max_length: 70
+///     thumbnail:
+///       type: string
+///       required: true
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub default_lang: String,
+    pub languages: Vec<LangConfig>,
+    pub site: SiteConfig,
+    pub frontmatter: FrontMatterConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            default_lang: "ja".to_string(),
+            languages: vec![
+                LangConfig {
+                    tag: "ja".to_string(),
+                    tokenizer: TokenizerKind::Lindera,
+                },
+                LangConfig {
+                    tag: "en".to_string(),
+                    tokenizer: TokenizerKind::Stemmer(Language::English),
+                },
+            ],
+            site: SiteConfig::default(),
+            frontmatter: FrontMatterConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    pub fn from_yaml_str(text: &str) -> Result<Self> {
+        let docs = YamlLoader::load_from_str(text)?;
+        let default = Self::default();
+        let doc = match docs.into_iter().next() {
+            Some(doc) => doc,
+            None => return Ok(default),
+        };
+
+        let languages = match &doc["languages"] {
+            Yaml::BadValue => default.languages,
+            Yaml::Hash(hash) => {
+                let mut languages = Vec::new();
+                for (tag, tokenizer) in hash.iter() {
+                    let (tag, tokenizer) = match (tag.as_str(), tokenizer.as_str()) {
+                        (Some(tag), Some(tokenizer)) => (tag, tokenizer),
+                        _ => return Err(anyhow!("languages must be a map of tag and tokenizer")),
+                    };
+                    languages.push(LangConfig {
+                        tag: Lang::canonicalize(tag)?,
+                        tokenizer: tokenizer.parse()?,
+                    });
+                }
+                languages
+            }
+            _ => return Err(anyhow!("languages must be a map of tag and tokenizer")),
+        };
+        if languages.is_empty() {
+            return Err(anyhow!("at least one language is needed in languages"));
+        }
+
+        let default_lang = match &doc["default_lang"] {
+            Yaml::BadValue => languages[0].tag.clone(),
+            Yaml::String(tag) => Lang::canonicalize(tag)?,
+            _ => return Err(anyhow!("default_lang must be string")),
+        };
+
+        let config = Self {
+            default_lang,
+            languages,
+            site: SiteConfig::from_yaml(&doc["site"])?,
+            frontmatter: FrontMatterConfig::from_yaml(&doc["frontmatter"])?,
+        };
+        if config.lang(&config.default_lang).is_none() {
+            return Err(anyhow!(
+                "default_lang {} is not in languages",
+                config.default_lang
+            ));
+        }
+        Ok(config)
+    }
+
+    pub fn from_path(path: &Path) -> Result<Self> {
+        Self::from_yaml_str(&read_string(path)?)
+            .with_context(|| format!("Error in config {:?}", path))
+    }
+
+    /// `tag` should be canonicalized by `Lang::canonicalize`
+    pub fn lang(&self, tag: &str) -> Option<&LangConfig> {
+        self.languages.iter().find(|lang| lang.tag == tag)
+    }
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Set the config used in the process. It can be set only once before the first `get`.
+#[cfg(not(tarpaulin_include))]
+pub fn init(config: Config) -> Result<()> {
+    CONFIG
+        .set(config)
+        .map_err(|_| anyhow!("config is already initialized"))
+}
+
+/// The config set by `init`, or the default config
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_config_from_yaml_str() -> Result<()> {
+        let config = Config::from_yaml_str(
+            "default_lang: EN\nlanguages:\n  ja: lindera\n  en: english\n  zh-hant-tw: ngram\n  de: German\n",
+        )?;
+        assert_eq!(config.default_lang, "en");
+        assert_eq!(
+            config
+                .languages
+                .iter()
+                .map(|l| l.tag.as_str())
+                .collect::<Vec<_>>(),
+            vec!["ja", "en", "zh-Hant-TW", "de"]
+        );
+        assert_eq!(
+            config.lang("de").unwrap().tokenizer,
+            TokenizerKind::Stemmer(Language::German)
+        );
+
+        assert_eq!(Config::from_yaml_str("")?, Config::default());
+        assert_eq!(
+            Config::from_yaml_str("languages:\n  ko: ngram\n")?.default_lang,
+            "ko"
+        );
+        assert!(Config::from_yaml_str("languages:\n  ko: unknown\n").is_err());
+        assert!(Config::from_yaml_str("default_lang: fr\nlanguages:\n  ko: ngram\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_site_config() -> Result<()> {
+        let config = Config::from_yaml_str(
+            "site:\n  title: Blog\n  base_url: https://example.com/\n  post_url: \"{base}/{category}/{slug}.{lang}\"\n",
+        )?;
+        assert_eq!(config.site.title, "Blog");
+        assert_eq!(config.site.author, None);
+        assert_eq!(
+            config.site.post_url("en", "hello", "uuid", "rust"),
+            "https://example.com/rust/hello.en"
+        );
+        assert_eq!(
+            SiteConfig::default().post_url("ja", "hello", "uuid", "rust"),
+            "http://localhost:8080/ja/posts/hello"
+        );
+        assert!(Config::from_yaml_str("site:\n  titel: Blog\n").is_err());
+        assert!(Config::from_yaml_str("site: Blog\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_frontmatter_config() -> Result<()> {
+        let config = Config::from_yaml_str(
+            "frontmatter:\n  strict: true\n  fields:\n    category:\n      enum: [programming, life]\n    thumbnail:\n      type: string\n      required: true\n      max_length: 100\n",
+        )?;
+        assert!(config.frontmatter.strict);
+        assert_eq!(
+            config.frontmatter.fields,
+            vec![
+                FieldRule {
+                    key: "category".to_string(),
+                    values: vec!["programming".to_string(), "life".to_string()],
+                    ..FieldRule::default()
+                },
+                FieldRule {
+                    key: "thumbnail".to_string(),
+                    kind: Some(ValueKind::String),
+                    required: true,
+                    values: Vec::new(),
+                    max_length: Some(100),
+                },
+            ]
+        );
+        assert_eq!(
+            Config::from_yaml_str("")?.frontmatter,
+            FrontMatterConfig::default()
+        );
+        assert!(
+            Config::from_yaml_str("frontmatter:\n  fields:\n    a:\n      type: text\n").is_err()
+        );
+        assert!(
+            Config::from_yaml_str("frontmatter:\n  fields:\n    a:\n      maxlength: 1\n").is_err()
+        );
+        assert!(Config::from_yaml_str("frontmatter:\n  strict: yes\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_lang_with_default_config() {
+        assert_eq!(Lang::from_str("EN").unwrap().as_str(), "en");
+        assert!(Lang::from_str("ko").is_err());
+        assert!(Lang::canonicalize("-en").is_err());
+        assert!(Lang::canonicalize("e").is_err());
+        assert_eq!(Lang::canonicalize("sr_latn_rs").unwrap(), "sr-Latn-RS");
+        assert_eq!(Lang::canonicalize("es-419").unwrap(), "es-419");
+    }
+
+    #[test]
+    fn test_lang_with_config() -> Result<()> {
+        let config = Config::from_yaml_str("languages:\n  ko: ngram\n  en: english\n")?;
+        let ko = Lang::from_str_in("KO", &config)?;
+        assert_eq!(
+            Lang::all_in(&config),
+            vec![ko.clone(), Lang::from_str("en")?]
+        );
+        assert!(Lang::from_str_in("ja", &config).is_err());
+        assert_eq!(ko.tokenizer_kind(&config), TokenizerKind::Ngram);
+        // the language out of the config falls back to the simple tokenizer
+        assert_eq!(
+            Lang::from_str("ja")?.tokenizer_kind(&config),
+            TokenizerKind::Simple
+        );
+        Ok(())
+    }
+}
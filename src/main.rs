@@ -2,10 +2,12 @@
 extern crate log;
 
 mod args;
+mod config;
 mod datetime;
 mod io;
 mod posts;
 mod server;
+mod site;
 mod text_engine;
 
 #[cfg(test)]
@@ -14,6 +16,7 @@ mod test_utility;
 use anyhow::{anyhow, Result};
 use std::env::set_var;
 use std::fs;
+use std::time::Duration;
 use structopt::StructOpt;
 use tantivy::collector::{Count, TopDocs};
 use tantivy::query::AllQuery;
@@ -43,12 +46,18 @@ fn main() -> Result<()> {
         None => set_var("RUST_LOG", "warn"),
     };
 
+    if let Some(config_path) = opt.config.as_ref() {
+        config::init(config::Config::from_path(config_path)?)?;
+    }
+
     match &opt.subcommand {
         SubCommands::Prep {
             input,
             index_dir,
             skip_update_date,
             rebuild,
+            watch,
+            watch_interval,
         } => {
             pretty_env_logger::init_timed();
             let glob_pattern = format!("{}/**/*.md", input.display());
@@ -61,6 +70,15 @@ fn main() -> Result<()> {
             let index = read_or_build_index(schema, index_dir, *rebuild)?;
 
             posts::index::build(&glob_pattern, &index, *skip_update_date)?;
+
+            if *watch {
+                posts::watch::watch(
+                    &glob_pattern,
+                    &index,
+                    *skip_update_date,
+                    Duration::from_millis(*watch_interval),
+                )?;
+            }
         }
 
         SubCommands::Run {
@@ -69,6 +87,8 @@ fn main() -> Result<()> {
             index_dir,
             _cors_origin,
             static_dir,
+            preview,
+            preview_token,
         } => {
             if !static_dir.exists() {
                 return Err(anyhow!(format!("{} does not exist", static_dir.display())));
@@ -79,6 +99,7 @@ fn main() -> Result<()> {
                 index_dir.to_owned(),
                 static_dir.to_owned(),
                 _cors_origin.to_owned(),
+                server::Preview::new(*preview, preview_token.to_owned()),
             )?;
         }
         SubCommands::Template {
@@ -165,6 +186,20 @@ fn main() -> Result<()> {
                 io::write_string(&outfile, &body)?;
             }
         }
+        SubCommands::Sitemap { index_dir, output } => {
+            pretty_env_logger::init_timed();
+            let index = Index::open_in_dir(index_dir)?;
+            let entries = site::sitemap::sitemap_entries(
+                &index,
+                &config::get().site,
+                text_engine::query::Visibility::listing(false),
+            )?;
+            let xml = site::sitemap::sitemap(&entries);
+            match output {
+                Some(output) => write_string(output, &xml)?,
+                None => print!("{}", xml),
+            }
+        }
     }
     Ok(())
 }
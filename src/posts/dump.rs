@@ -34,6 +34,8 @@ pub fn dump_doc(doc: &Document, schema: &Schema) -> Result<(String, String)> {
 #[cfg(test)]
 mod test {
     use crate::datetime::DateTimeWithFormat;
+    use crate::posts::frontmatter::split_frontmatter_and_content;
+    use crate::text_engine::schema::build_schema;
 
     use super::*;
 
@@ -45,7 +47,7 @@ mod test {
             "title",
             "description",
             "category",
-            crate::posts::Lang::En,
+            crate::posts::Lang::from_str("en").unwrap(),
             None,
             Some(now.clone()),
             Some(now.clone()),
@@ -58,4 +60,44 @@ mod test {
 
         assert_eq!(out, expected);
     }
+
+    #[test]
+    fn test_dump_doc_with_extra() -> Result<()> {
+        let text = "---\nuuid: uuid\ntitle: title\ndescription: description\ncategory: category\nlang: en\nthumbnail: a.png\nseries:\n  - rust\n  - 1.5\n---\nbody\n";
+        let (matter, body) = split_frontmatter_and_content(text)?;
+        let post = Post::new("slug".to_string(), matter.unwrap(), body.to_string());
+
+        let (_, dumped) = dump_post(&post)?;
+        assert!(dumped.contains("thumbnail: a.png\nseries:\n  - rust\n  - 1.5\n---\n"));
+
+        let schema = build_schema();
+        let now = DateTimeWithFormat::default();
+        let doc = post.to_doc(&schema, &now, &now);
+        let (filename, dumped_doc) = dump_doc(&doc, &schema)?;
+        assert_eq!(filename, "en/slug.md");
+        assert!(dumped_doc.contains("thumbnail: a.png\nseries:\n  - rust\n  - 1.5\n---\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_dump_doc_lossy_extra() -> Result<()> {
+        let text = "---\nuuid: uuid\nratio: 1.50\ntitle: title\ndescription: description\ncategory: category\nlang: en\nscore: .nan\nparts:\n  1: one\n  true: two\n---\nbody\n";
+        let (matter, body) = split_frontmatter_and_content(text)?;
+        let post = Post::new("slug".to_string(), matter.unwrap(), body.to_string());
+
+        // the parsed post is written back as it is
+        let (_, dumped) = dump_post(&post)?;
+        assert!(dumped.starts_with("---\nuuid: uuid\nratio: 1.50\ntitle: title\n"));
+        assert!(dumped.contains("score: .nan\nparts:\n  1: one\n  true: two\n"));
+
+        // the index keeps extra keys as json, so the order of the keys, the format of reals,
+        // scalar keys and the values out of json are not restored
+        let schema = build_schema();
+        let now = DateTimeWithFormat::default();
+        let doc = post.to_doc(&schema, &now, &now);
+        let (_, dumped_doc) = dump_doc(&doc, &schema)?;
+        assert!(dumped_doc
+            .contains("ratio: 1.5\nscore: ~\nparts:\n  \"1\": one\n  \"true\": two\n---\n"));
+        Ok(())
+    }
 }
@@ -92,6 +92,20 @@ pub fn extract_text(markdown_text: &str) -> Result<String> {
     Ok(s.join("\n"))
 }
 
+/// Make excerpt from the first `length` characters of the text.
+/// Whitespaces including newlines are collapsed into a space.
+pub fn excerpt(text: &str, length: usize) -> String {
+    let collapsed = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+
+    if collapsed.chars().count() <= length {
+        collapsed
+    } else {
+        let mut s: String = collapsed.chars().take(length).collect();
+        s.push('…');
+        s
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -176,4 +190,11 @@ comments
         assert_eq!(text, expected);
         Ok(())
     }
+
+    #[test]
+    fn test_excerpt() {
+        assert_eq!(excerpt("TEST\nこれは  テスト", 100), "TEST これは テスト");
+        assert_eq!(excerpt("TEST\nこれはテスト", 7), "TEST これ…");
+        assert_eq!(excerpt("", 7), "");
+    }
 }
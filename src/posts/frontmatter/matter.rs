@@ -1,14 +1,45 @@
 use anyhow::{anyhow, Result};
+use linked_hash_map::LinkedHashMap;
+use serde_json::{Map, Value};
 use uuid::Uuid;
 use yaml_rust::{Yaml, YamlLoader};
 
+use super::validate::{MarkedYaml, Violations};
 use super::yaml::{
-    get_or_fill_str_from_yaml, get_str_from_yaml, get_tags_from_yaml, matter_to_yaml,
-    parse_date_from_yaml,
+    get_bool_from_yaml, get_extra_from_yaml, get_or_fill_str_from_yaml, get_str_from_yaml,
+    get_tags_from_yaml, json_map_to_yaml, matter_to_yaml, parse_date_from_yaml, yaml_hash_to_json,
 };
 use crate::{datetime::DateTimeWithFormat, posts::Lang, text_engine::schema::PostField};
 
-#[derive(Debug, Clone, PartialEq)]
+/// Publication state of the post
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Publication {
+    pub draft: bool,
+    /// the post is hidden until this date
+    pub publish_at: Option<DateTimeWithFormat>,
+    /// the post is resolved by slug or uuid, but not listed
+    pub unlisted: bool,
+}
+
+impl Publication {
+    fn from_yaml(doc: &Yaml) -> Result<Self> {
+        Ok(Self {
+            draft: get_bool_from_yaml(doc, PostField::Draft)?,
+            publish_at: parse_date_from_yaml(doc, PostField::PublishAt)?,
+            unlisted: get_bool_from_yaml(doc, PostField::Unlisted)?,
+        })
+    }
+
+    /// `publish_at` is compared by the datetime because the format is not indexed
+    fn equal_from_doc(&self, other: &Self) -> bool {
+        self.draft == other.draft
+            && self.unlisted == other.unlisted
+            && self.publish_at.as_ref().map(|d| d.datetime())
+                == other.publish_at.as_ref().map(|d| d.datetime())
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct FrontMatter {
     uuid: String,
     title: String,
@@ -18,6 +49,30 @@ pub struct FrontMatter {
     pub tags: Option<Vec<String>>,
     created_at: Option<DateTimeWithFormat>,
     updated_at: Option<DateTimeWithFormat>,
+    /// uuid of the original post, to link translations with different slugs
+    translation_of: Option<String>,
+    publication: Publication,
+    /// keys not used by smark, such as `thumbnail`, kept to be written back
+    extra: LinkedHashMap<Yaml, Yaml>,
+    /// top level keys of the parsed frontmatter, to be written back in the same order
+    key_order: Vec<Yaml>,
+}
+
+/// The order of the keys is formatting of the markdown, so it is not compared
+impl PartialEq for FrontMatter {
+    fn eq(&self, other: &Self) -> bool {
+        self.uuid == other.uuid
+            && self.title == other.title
+            && self.description == other.description
+            && self.lang == other.lang
+            && self.category == other.category
+            && self.tags == other.tags
+            && self.created_at == other.created_at
+            && self.updated_at == other.updated_at
+            && self.translation_of == other.translation_of
+            && self.publication == other.publication
+            && self.extra == other.extra
+    }
 }
 
 impl FrontMatter {
@@ -47,9 +102,40 @@ impl FrontMatter {
             tags,
             created_at,
             updated_at,
+            translation_of: None,
+            publication: Publication::default(),
+            extra: LinkedHashMap::new(),
+            key_order: Vec::new(),
         }
     }
 
+    pub fn with_translation_of(mut self, translation_of: Option<String>) -> Self {
+        self.translation_of = translation_of;
+        self
+    }
+
+    pub fn with_publication(mut self, publication: Publication) -> Self {
+        self.publication = publication;
+        self
+    }
+
+    /// Keep the extra keys and the order of the keys of the parsed frontmatter
+    fn with_source(mut self, doc: &Yaml) -> Self {
+        self.extra = get_extra_from_yaml(doc);
+        self.key_order = doc
+            .as_hash()
+            .map(|hash| hash.keys().cloned().collect())
+            .unwrap_or_default();
+        self
+    }
+
+    /// Extra keys stored in the index as json.
+    /// The order of the keys, the format of reals and non-string keys are not restored.
+    pub fn with_extra_json(mut self, extra: &Map<String, Value>) -> Self {
+        self.extra = json_map_to_yaml(extra);
+        self
+    }
+
     pub fn uuid(&self) -> String {
         self.uuid.clone()
     }
@@ -86,6 +172,26 @@ impl FrontMatter {
         &mut self.updated_at
     }
 
+    pub fn translation_of(&self) -> Option<String> {
+        self.translation_of.clone()
+    }
+
+    pub fn publication(&self) -> Publication {
+        self.publication.clone()
+    }
+
+    pub fn extra(&self) -> &LinkedHashMap<Yaml, Yaml> {
+        &self.extra
+    }
+
+    pub fn extra_json(&self) -> Map<String, Value> {
+        yaml_hash_to_json(&self.extra)
+    }
+
+    pub fn key_order(&self) -> &[Yaml] {
+        &self.key_order
+    }
+
     /// **CAUSION!**  
     /// This function do not return strict equal.
     /// If updated_at and created_at in `self.matter` is `None`,
@@ -99,6 +205,9 @@ impl FrontMatter {
             && self.category == other.category
             && self.tags == other.tags
             && self.lang == other.lang
+            && self.translation_of == other.translation_of
+            && self.publication.equal_from_doc(&other.publication)
+            && self.extra_json() == other.extra_json()
     }
 
     pub fn to_yaml(&self) -> Yaml {
@@ -132,7 +241,7 @@ pub fn replace_frontmatter(
     updated_at: &Option<DateTimeWithFormat>,
 ) -> Result<FrontMatter> {
     let docs = if frontmatter.is_empty() {
-        YamlLoader::load_from_str("empty: f")?
+        YamlLoader::load_from_str("{}")?
     } else {
         YamlLoader::load_from_str(frontmatter)?
     };
@@ -141,12 +250,17 @@ pub fn replace_frontmatter(
     let title = get_or_fill_str_from_yaml(doc, PostField::Title, title, "");
     let category = get_or_fill_str_from_yaml(doc, PostField::Category, category, "");
     let description = get_or_fill_str_from_yaml(doc, PostField::Description, description, "");
-    let lang = Lang::from_str(&get_or_fill_str_from_yaml(doc, PostField::Lang, lang, "ja"))?;
+    let lang = Lang::from_str(&get_or_fill_str_from_yaml(
+        doc,
+        PostField::Lang,
+        lang,
+        Lang::default_lang(),
+    ))?;
 
     let tags = if let Some(tags) = tags {
         Some(tags.to_owned())
     } else {
-        get_tags_from_yaml(doc)
+        get_tags_from_yaml(doc)?
     };
 
     let created_at = if let Some(created_at) = created_at {
@@ -160,6 +274,8 @@ pub fn replace_frontmatter(
     } else {
         parse_date_from_yaml(doc, PostField::UpdatedAt)?
     };
+    let translation_of = get_str_from_yaml(doc, PostField::TranslationOf).ok();
+    let publication = Publication::from_yaml(doc)?;
 
     Ok(FrontMatter::new(
         uuid,
@@ -170,21 +286,29 @@ pub fn replace_frontmatter(
         tags,
         created_at,
         updated_at,
-    ))
+    )
+    .with_translation_of(translation_of)
+    .with_publication(publication)
+    .with_source(doc))
 }
 
-pub fn parse_frontmatter(frontmatter: &str) -> Result<FrontMatter> {
-    let docs = YamlLoader::load_from_str(frontmatter)?;
-    let doc = &docs[0];
+/// Parse the loaded frontmatter. All problems of the keys are reported with their lines.
+pub fn parse_frontmatter(yaml: &MarkedYaml) -> Result<FrontMatter> {
+    let violations = yaml.check_matter();
+    if !violations.is_empty() {
+        return Err(Violations(violations).into());
+    }
+
+    let doc = &yaml.doc;
     let uuid = get_str_from_yaml(doc, PostField::Uuid)?;
     let title = get_str_from_yaml(doc, PostField::Title)?;
     let category = get_str_from_yaml(doc, PostField::Category)?;
     let description = get_str_from_yaml(doc, PostField::Description)?;
 
-    let tags = get_tags_from_yaml(doc);
+    let tags = get_tags_from_yaml(doc)?;
 
     let lang = match &doc[PostField::Lang.as_str()] {
-        Yaml::BadValue => Lang::Ja,
+        Yaml::BadValue => Lang::default_lang(),
         Yaml::String(s) => Lang::from_str(s)?,
         _ => return Err(anyhow!("Unsupported lang type. Lang must be string")),
     };
@@ -192,6 +316,13 @@ pub fn parse_frontmatter(frontmatter: &str) -> Result<FrontMatter> {
     let created_at = parse_date_from_yaml(doc, PostField::CreatedAt)?;
     let updated_at = parse_date_from_yaml(doc, PostField::UpdatedAt)?;
 
+    let translation_of = match &doc[PostField::TranslationOf.as_str()] {
+        Yaml::BadValue | Yaml::Null => None,
+        Yaml::String(s) => Some(s.to_owned()),
+        _ => return Err(anyhow!("translation_of must be uuid string")),
+    };
+    let publication = Publication::from_yaml(doc)?;
+
     Ok(FrontMatter::new(
         uuid,
         title,
@@ -201,7 +332,10 @@ pub fn parse_frontmatter(frontmatter: &str) -> Result<FrontMatter> {
         tags,
         created_at,
         updated_at,
-    ))
+    )
+    .with_translation_of(translation_of)
+    .with_publication(publication)
+    .with_source(doc))
 }
 
 pub fn find_frontmatter_block(text: &str) -> Option<(usize, usize)> {
@@ -219,16 +353,23 @@ pub fn find_frontmatter_block(text: &str) -> Option<(usize, usize)> {
     }
 }
 
-pub fn split_frontmatter_and_content(text: &str) -> (Option<FrontMatter>, &str) {
+/// Loaded frontmatter and the content after it
+pub fn split_marked_frontmatter(text: &str) -> Result<(Option<MarkedYaml>, &str)> {
     match find_frontmatter_block(text) {
-        Some((fm_start, fm_end)) => (
-            Some(parse_frontmatter(&text[fm_start..fm_end]).unwrap()),
+        Some((fm_start, fm_end)) => Ok((
+            Some(MarkedYaml::load(&text[fm_start..fm_end])?),
             &text[fm_end..],
-        ),
-        None => (None, text),
+        )),
+        None => Ok((None, text)),
     }
 }
 
+#[cfg(test)]
+pub fn split_frontmatter_and_content(text: &str) -> Result<(Option<FrontMatter>, &str)> {
+    let (yaml, content) = split_marked_frontmatter(text)?;
+    Ok((yaml.as_ref().map(parse_frontmatter).transpose()?, content))
+}
+
 #[cfg(test)]
 mod test {
     use chrono::Utc;
@@ -247,13 +388,13 @@ mod test {
     fn test_frontmatter() {
         let test_string = "---\nuuid: uuid\ntitle: Valid Yaml Test\ndescription: Valid Yaml Description\ncategory: Valid Yaml category\n---\nsomething that's not yaml";
 
-        let (frontmatter, content) = split_frontmatter_and_content(test_string);
+        let (frontmatter, content) = split_frontmatter_and_content(test_string).unwrap();
         let expect_frontmatter = FrontMatter::new(
             "uuid".to_string(),
             "Valid Yaml Test".to_string(),
             "Valid Yaml Description".to_string(),
             "Valid Yaml category".to_string(),
-            Lang::Ja,
+            Lang::default_lang(),
             None,
             None,
             None,
@@ -266,14 +407,116 @@ mod test {
     fn test_frontmatter_tags() {
         let test_string_tags = "---\nuuid: uuid\n\ntitle: Valid Yaml Test\ndescription: Valid Yaml Description\ncategory: Valid Yaml category\ntags:\n- '1'\n- '2'\n---\nsomething that's not yaml";
         let test_int_tags = "---\nuuid: uuid\n\ntitle: Valid Yaml Test\ndescription: Valid Yaml Description\ncategory: Valid Yaml category\ntags:\n- 1\n- 2\n---\nsomething that's not yaml";
-        let (string_frontmatter, _) = split_frontmatter_and_content(test_string_tags);
-        let (int_frontmatter, _) = split_frontmatter_and_content(test_int_tags);
+        let (string_frontmatter, _) = split_frontmatter_and_content(test_string_tags).unwrap();
+        let (int_frontmatter, _) = split_frontmatter_and_content(test_int_tags).unwrap();
         assert_eq!(
             string_frontmatter.expect("error in string"),
             int_frontmatter.expect("error in int")
         );
     }
 
+    #[test]
+    fn test_frontmatter_translation_of() {
+        let test_string = "---\nuuid: uuid\ntitle: Test\ndescription: Test\ncategory: Test\nlang: en\ntranslation_of: original-uuid\n---\n";
+        let (frontmatter, _) = split_frontmatter_and_content(test_string).unwrap();
+        let frontmatter = frontmatter.unwrap();
+        assert_eq!(
+            frontmatter.translation_of(),
+            Some("original-uuid".to_string())
+        );
+
+        let mut out_str = String::new();
+        let mut emitter = YamlEmitter::new(&mut out_str);
+        emitter.dump(&frontmatter.to_yaml()).unwrap();
+        assert!(out_str.ends_with("lang: en\ntranslation_of: original-uuid"));
+
+        assert!(split_frontmatter_and_content(
+            "---\nuuid: uuid\ntitle: Test\ndescription: Test\ncategory: Test\ntranslation_of:\n  - a\n---\n"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_frontmatter_publication() {
+        let test_string = "---\nuuid: uuid\ntitle: Test\ndescription: Test\ncategory: Test\ndraft: true\npublish_at: \"2030-01-01\"\n---\n";
+        let (frontmatter, _) = split_frontmatter_and_content(test_string).unwrap();
+        let publication = frontmatter.clone().unwrap().publication();
+        assert!(publication.draft);
+        assert!(!publication.unlisted);
+        assert_eq!(
+            publication.publish_at,
+            Some(DateTimeWithFormat::from_str("2030-01-01").unwrap())
+        );
+
+        let mut out_str = String::new();
+        let mut emitter = YamlEmitter::new(&mut out_str);
+        emitter.dump(&frontmatter.unwrap().to_yaml()).unwrap();
+        assert!(out_str.ends_with("draft: true\npublish_at: 2030-01-01\nlang: ja"));
+
+        assert!(split_frontmatter_and_content(
+            "---\nuuid: uuid\ntitle: Test\ndescription: Test\ncategory: Test\nunlisted: yes please\n---\n"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_frontmatter_extra() {
+        let test_string = "---\nuuid: uuid\nthumbnail: /img/a.png\ntitle: Test\ndescription: Test\ncategory: Test\nseries:\n  name: rust\n  part: 2\ncanonical_url: \"https://example.com/a\"\n---\n";
+        let (frontmatter, _) = split_frontmatter_and_content(test_string).unwrap();
+        let frontmatter = frontmatter.unwrap();
+        assert_eq!(
+            frontmatter
+                .extra()
+                .keys()
+                .map(|k| k.as_str().unwrap())
+                .collect::<Vec<_>>(),
+            vec!["thumbnail", "series", "canonical_url"]
+        );
+        assert_eq!(frontmatter.extra_json()["series"]["part"], 2);
+
+        let mut out_str = String::new();
+        let mut emitter = YamlEmitter::new(&mut out_str);
+        emitter.dump(&frontmatter.to_yaml()).unwrap();
+        assert_eq!(
+            out_str,
+            "---\nuuid: uuid\nthumbnail: /img/a.png\ntitle: Test\ndescription: Test\ncategory: Test\nseries:\n  name: rust\n  part: 2\ncanonical_url: \"https://example.com/a\"\nlang: ja"
+        );
+
+        // extra keys are restored from json in the index
+        let from_doc = FrontMatter::new(
+            "uuid",
+            "Test",
+            "Test",
+            "Test",
+            Lang::default_lang(),
+            None,
+            None,
+            None,
+        )
+        .with_extra_json(&frontmatter.extra_json());
+        assert_eq!(from_doc, frontmatter);
+        let mut changed = frontmatter.clone();
+        changed.extra.remove(&Yaml::String("thumbnail".to_string()));
+        assert!(!frontmatter.equal_matter_from_doc(&changed));
+
+        let replaced = replace_frontmatter(
+            test_string,
+            &None,
+            &Some("New".to_string()),
+            &None,
+            &None,
+            &None,
+            &None,
+            &None,
+            &None,
+        )
+        .unwrap();
+        assert_eq!(replaced.extra(), frontmatter.extra());
+        let empty = replace_frontmatter("", &None, &None, &None, &None, &None, &None, &None, &None)
+            .unwrap();
+        assert!(empty.extra().is_empty());
+    }
+
     #[test]
     fn test_frontmatter_to_yaml() {
         let test_string_tags = "---
@@ -291,14 +534,14 @@ tags:
 
 ## TEST
 ";
-        let (frontmatter, _) = split_frontmatter_and_content(test_string_tags);
+        let (frontmatter, _) = split_frontmatter_and_content(test_string_tags).unwrap();
         let mut out_str = String::new();
         let mut emitter = YamlEmitter::new(&mut out_str);
         emitter
             .dump(&frontmatter.clone().unwrap().to_yaml())
             .unwrap();
         out_str.push_str("\n---\n");
-        let (out_frontmatter, _) = split_frontmatter_and_content(&out_str);
+        let (out_frontmatter, _) = split_frontmatter_and_content(&out_str).unwrap();
         assert_eq!(frontmatter.unwrap(), out_frontmatter.unwrap());
     }
 
@@ -329,8 +572,8 @@ tags:
     - \"2\"
 ---
 ";
-        let (frontmatter_with_date, _) = split_frontmatter_and_content(test_with_date);
-        let (frontmatter_no_date, _) = split_frontmatter_and_content(test_no_date);
+        let (frontmatter_with_date, _) = split_frontmatter_and_content(test_with_date).unwrap();
+        let (frontmatter_no_date, _) = split_frontmatter_and_content(test_no_date).unwrap();
         let frontmatter_with_date = frontmatter_with_date.unwrap();
         let frontmatter_no_date = frontmatter_no_date.unwrap();
         assert!(frontmatter_no_date.equal_matter_from_doc(&frontmatter_no_date));
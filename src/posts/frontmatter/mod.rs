@@ -1,4 +1,6 @@
 mod matter;
+mod validate;
 mod yaml;
 
 pub use matter::*;
+pub use validate::*;
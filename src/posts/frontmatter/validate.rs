@@ -0,0 +1,374 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use anyhow::Result;
+use yaml_rust::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust::scanner::{Marker, ScanError};
+use yaml_rust::{Yaml, YamlLoader};
+
+use crate::config::{FieldRule, FrontMatterConfig, ValueKind};
+use crate::datetime::DateTimeWithFormat;
+use crate::posts::Lang;
+use crate::text_engine::schema::PostField;
+
+/// Problem of the frontmatter at `line` of the markdown
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Violation {
+    /// `path:line: message`, the format of the violations of the markdown
+    pub fn at(&self, path: &Path) -> String {
+        format!("{}:{}: {}", path.display(), self.line, self.message)
+    }
+}
+
+/// Violations of the keys read by smark, which is the error of `parse_frontmatter`
+#[derive(Debug)]
+pub struct Violations(pub Vec<Violation>);
+
+impl fmt::Display for Violations {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let lines: Vec<String> = self.0.iter().map(|v| v.to_string()).collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+impl std::error::Error for Violations {}
+
+impl Violations {
+    pub fn at(&self, path: &Path) -> String {
+        let lines: Vec<String> = self.0.iter().map(|v| v.at(path)).collect();
+        lines.join("\n")
+    }
+}
+
+/// Lines of the top level keys, collected from the events of the yaml parser
+#[derive(Default)]
+struct KeyLines {
+    depth: usize,
+    nodes: usize,
+    lines: HashMap<String, usize>,
+}
+
+impl MarkedEventReceiver for KeyLines {
+    fn on_event(&mut self, ev: Event, mark: Marker) {
+        let is_node = matches!(
+            ev,
+            Event::Scalar(..) | Event::Alias(_) | Event::MappingStart(_) | Event::SequenceStart(_)
+        );
+        // nodes of the top level mapping are keys and values by turns
+        if is_node && self.depth == 1 {
+            if let (0, Event::Scalar(key, ..)) = (self.nodes % 2, &ev) {
+                self.lines.entry(key.to_owned()).or_insert(mark.line());
+            }
+            self.nodes += 1;
+        }
+        match ev {
+            Event::MappingStart(_) | Event::SequenceStart(_) => self.depth += 1,
+            Event::MappingEnd | Event::SequenceEnd => self.depth -= 1,
+            _ => (),
+        }
+    }
+}
+
+/// Parsed frontmatter with the lines of its keys.
+/// The frontmatter is at the top of the markdown, so the lines are also the lines of the file.
+pub struct MarkedYaml {
+    pub doc: Yaml,
+    lines: HashMap<String, usize>,
+}
+
+impl MarkedYaml {
+    /// Errors of the yaml parser are `Violations` at their lines
+    pub fn load(frontmatter: &str) -> Result<Self> {
+        let to_violations = |e: ScanError| {
+            Violations(vec![Violation {
+                line: e.marker().line(),
+                message: e.to_string(),
+            }])
+        };
+        let doc = YamlLoader::load_from_str(frontmatter)
+            .map_err(to_violations)?
+            .into_iter()
+            .next()
+            .unwrap_or(Yaml::BadValue);
+        let mut key_lines = KeyLines::default();
+        Parser::new(frontmatter.chars())
+            .load(&mut key_lines, false)
+            .map_err(to_violations)?;
+        Ok(Self {
+            doc,
+            lines: key_lines.lines,
+        })
+    }
+
+    /// Line of the key, or the first line of the frontmatter if the key is missing
+    fn line(&self, key: &str) -> usize {
+        self.lines.get(key).copied().unwrap_or(1)
+    }
+
+    fn violation(&self, key: &str, message: String) -> Violation {
+        Violation {
+            line: self.line(key),
+            message,
+        }
+    }
+
+    /// Violations of the keys read by `parse_frontmatter`
+    pub fn check_matter(&self) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        let doc = &self.doc;
+        if doc.as_hash().is_none() {
+            violations.push(Violation {
+                line: 1,
+                message: "frontmatter must be a map".to_string(),
+            });
+            return violations;
+        }
+
+        for pf in [
+            PostField::Uuid,
+            PostField::Title,
+            PostField::Description,
+            PostField::Category,
+        ] {
+            let key = pf.as_str();
+            match &doc[key] {
+                Yaml::String(_) | Yaml::Integer(_) => (),
+                Yaml::BadValue | Yaml::Null => {
+                    violations.push(self.violation(key, format!("{} is required", key)))
+                }
+                _ => violations.push(self.violation(key, format!("{} must be a string", key))),
+            }
+        }
+
+        let lang = PostField::Lang.as_str();
+        match &doc[lang] {
+            Yaml::BadValue => (),
+            Yaml::String(s) => {
+                if let Err(e) = Lang::from_str(s) {
+                    violations.push(self.violation(lang, e.to_string()));
+                }
+            }
+            _ => violations.push(self.violation(lang, format!("{} must be a string", lang))),
+        }
+
+        let tags = PostField::Tags.as_str();
+        match &doc[tags] {
+            Yaml::BadValue | Yaml::Null => (),
+            Yaml::Array(items)
+                if items
+                    .iter()
+                    .all(|item| matches!(item, Yaml::String(_) | Yaml::Integer(_))) => {}
+            _ => violations.push(self.violation(
+                tags,
+                format!("{} must be a list of strings or integers", tags),
+            )),
+        }
+
+        for pf in [
+            PostField::CreatedAt,
+            PostField::UpdatedAt,
+            PostField::PublishAt,
+        ] {
+            let key = pf.as_str();
+            match &doc[key] {
+                Yaml::BadValue | Yaml::Null => (),
+                Yaml::String(s) if DateTimeWithFormat::from_str(s).is_ok() => (),
+                _ => violations.push(self.violation(key, format!("{} must be a date", key))),
+            }
+        }
+
+        let translation_of = PostField::TranslationOf.as_str();
+        if !matches!(
+            &doc[translation_of],
+            Yaml::BadValue | Yaml::Null | Yaml::String(_)
+        ) {
+            violations.push(self.violation(
+                translation_of,
+                format!("{} must be uuid string", translation_of),
+            ));
+        }
+
+        for pf in [PostField::Draft, PostField::Unlisted] {
+            let key = pf.as_str();
+            if !matches!(&doc[key], Yaml::BadValue | Yaml::Null | Yaml::Boolean(_)) {
+                violations.push(self.violation(key, format!("{} must be true or false", key)));
+            }
+        }
+
+        violations
+    }
+
+    /// Check the frontmatter by the rules in config, sorted by the lines
+    pub fn check_rules(&self, config: &FrontMatterConfig) -> Vec<Violation> {
+        let mut violations: Vec<Violation> = config
+            .fields
+            .iter()
+            .flat_map(|rule| self.check_rule(rule))
+            .collect();
+        violations.sort_by_key(|v| v.line);
+        violations
+    }
+
+    fn check_rule(&self, rule: &FieldRule) -> Vec<Violation> {
+        let key = rule.key.as_str();
+        let value = &self.doc[key];
+        if matches!(value, Yaml::BadValue | Yaml::Null) {
+            return match rule.required {
+                true => vec![self.violation(key, format!("{} is required", key))],
+                false => Vec::new(),
+            };
+        }
+
+        let mut violations = Vec::new();
+        if let Some(kind) = rule.kind {
+            let ok = match kind {
+                ValueKind::String => matches!(value, Yaml::String(_)),
+                ValueKind::Integer => matches!(value, Yaml::Integer(_)),
+                ValueKind::Number => matches!(value, Yaml::Integer(_) | Yaml::Real(_)),
+                ValueKind::Bool => matches!(value, Yaml::Boolean(_)),
+                ValueKind::List => matches!(value, Yaml::Array(_)),
+                ValueKind::Map => matches!(value, Yaml::Hash(_)),
+                ValueKind::Date => value
+                    .as_str()
+                    .is_some_and(|s| DateTimeWithFormat::from_str(s).is_ok()),
+            };
+            if !ok {
+                violations.push(self.violation(key, format!("{} must be {}", key, kind.as_str())));
+            }
+        }
+
+        if !rule.values.is_empty() {
+            let items = match value {
+                Yaml::Array(items) => items.iter().collect(),
+                _ => vec![value],
+            };
+            for item in items {
+                let text = match item {
+                    Yaml::String(s) => s.to_owned(),
+                    Yaml::Integer(i) => i.to_string(),
+                    _ => format!("{:?}", item),
+                };
+                if !rule.values.contains(&text) {
+                    violations.push(self.violation(
+                        key,
+                        format!(
+                            "{} must be one of {}, but is {}",
+                            key,
+                            rule.values.join(", "),
+                            text
+                        ),
+                    ));
+                }
+            }
+        }
+
+        if let (Some(max_length), Yaml::String(s)) = (rule.max_length, value) {
+            let length = s.chars().count();
+            if length > max_length {
+                violations.push(self.violation(
+                    key,
+                    format!(
+                        "{} has {} characters, more than {}",
+                        key, length, max_length
+                    ),
+                ));
+            }
+        }
+
+        violations
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn test_check_matter() -> Result<()> {
+        let yaml = MarkedYaml::load(
+            "---\nuuid: uuid\ntitle:\n  - a\ncategory: Test\ntags:\n  - rust\n  - [nested]\ncreated_at: yesterday\ndraft: \"true\"\n---\n",
+        )?;
+        assert_eq!(
+            yaml.check_matter()
+                .into_iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "line 3: title must be a string",
+                "line 1: description is required",
+                "line 6: tags must be a list of strings or integers",
+                "line 9: created_at must be a date",
+                "line 10: draft must be true or false",
+            ]
+        );
+
+        let yaml = MarkedYaml::load(
+            "---\nuuid: uuid\ntitle: Test\ndescription: Test\ncategory: Test\n---\n",
+        )?;
+        assert!(yaml.check_matter().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_marked_yaml_load() -> Result<()> {
+        let text = "---\nuuid: uuid\n\"title\": Test\nseries: &s\n  name: rust\n  parts: [1, 2.50, .nan]\nsame: *s\ntags:\n  - !!int 1\n  - !!float .inf\n  - !!str true\n  - ~\n? complex\n: key\n---\n";
+        let yaml = MarkedYaml::load(text)?;
+        assert_eq!(
+            ["uuid", "title", "series", "same", "tags", "complex"].map(|key| yaml.line(key)),
+            [2, 3, 4, 7, 8, 13]
+        );
+        assert_eq!(yaml.line("name"), 1);
+
+        let e = MarkedYaml::load("---\nuuid: uuid\ntitle: [a\n---\n")
+            .err()
+            .unwrap();
+        let violations = e.downcast_ref::<Violations>().unwrap();
+        assert_eq!(violations.0.len(), 1);
+        assert_eq!(violations.0[0].line, 4);
+        assert!(violations.at(Path::new("a.md")).starts_with("a.md:4: "));
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_rules() -> Result<()> {
+        let config = Config::from_yaml_str(
+            "frontmatter:\n  fields:\n    category:\n      enum: [programming, life]\n    title:\n      max_length: 5\n    thumbnail:\n      required: true\n    series:\n      type: map\n    tags:\n      enum: [rust]\n",
+        )?
+        .frontmatter;
+        let yaml = MarkedYaml::load(
+            "---\nuuid: uuid\ntitle: Long Title\ndescription: Test\ncategory: travel\nseries: rust\ntags:\n  - rust\n  - go\n---\n",
+        )?;
+        assert_eq!(
+            yaml.check_rules(&config)
+                .into_iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "line 1: thumbnail is required",
+                "line 3: title has 10 characters, more than 5",
+                "line 5: category must be one of programming, life, but is travel",
+                "line 6: series must be map",
+                "line 7: tags must be one of rust, but is go",
+            ]
+        );
+
+        let yaml = MarkedYaml::load(
+            "---\nuuid: uuid\ntitle: Short\ndescription: Test\ncategory: life\nthumbnail: a.png\nseries:\n  name: rust\n---\n",
+        )?;
+        assert!(yaml.check_rules(&config).is_empty());
+        Ok(())
+    }
+}
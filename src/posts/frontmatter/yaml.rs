@@ -1,5 +1,6 @@
 use anyhow::{anyhow, Result};
 use linked_hash_map::LinkedHashMap;
+use serde_json::{Map, Number, Value};
 use yaml_rust::Yaml;
 
 use crate::{datetime::DateTimeWithFormat, text_engine::schema::PostField};
@@ -11,10 +12,8 @@ pub(super) fn get_str_from_yaml(doc: &Yaml, field: PostField) -> Result<String>
     match &doc[field_str] {
         Yaml::String(s) => Ok(s.to_owned()),
         Yaml::Integer(i) => Ok(i.to_string()),
-        _ => Err(anyhow!(format!(
-            "{} field is need in frontmatter",
-            field_str
-        ))),
+        Yaml::BadValue | Yaml::Null => Err(anyhow!("{} is required in frontmatter", field_str)),
+        _ => Err(anyhow!("{} must be a string", field_str)),
     }
 }
 
@@ -30,16 +29,28 @@ pub(super) fn parse_date_from_yaml(
         })
 }
 
-pub(super) fn get_tags_from_yaml(doc: &Yaml) -> Option<Vec<String>> {
-    doc[PostField::Tags.as_str()].as_vec().map(|t| {
-        t.iter()
-            .map(|ss| match ss {
-                Yaml::Integer(i) => i.to_string(),
-                Yaml::String(s) => s.to_owned(),
-                _ => panic!("Unsupported tag type. Tags must be intger or string"),
-            })
-            .collect()
-    })
+/// `false` if the key is missing
+pub(super) fn get_bool_from_yaml(doc: &Yaml, field: PostField) -> Result<bool> {
+    match &doc[field.as_str()] {
+        Yaml::BadValue | Yaml::Null => Ok(false),
+        Yaml::Boolean(b) => Ok(*b),
+        _ => Err(anyhow!("{} must be true or false", field.as_str())),
+    }
+}
+
+pub(super) fn get_tags_from_yaml(doc: &Yaml) -> Result<Option<Vec<String>>> {
+    doc[PostField::Tags.as_str()]
+        .as_vec()
+        .map(|t| {
+            t.iter()
+                .map(|ss| match ss {
+                    Yaml::Integer(i) => Ok(i.to_string()),
+                    Yaml::String(s) => Ok(s.to_owned()),
+                    _ => Err(anyhow!("tags must be a list of strings or integers")),
+                })
+                .collect()
+        })
+        .transpose()
 }
 
 pub(super) fn get_or_fill_str_from_yaml<S: ToString>(
@@ -57,6 +68,90 @@ pub(super) fn get_or_fill_str_from_yaml<S: ToString>(
     }
 }
 
+/// keys of the frontmatter read into the fields of `FrontMatter`
+const MATTER_FIELDS: [PostField; 12] = [
+    PostField::Uuid,
+    PostField::Title,
+    PostField::Description,
+    PostField::Lang,
+    PostField::Category,
+    PostField::Tags,
+    PostField::CreatedAt,
+    PostField::UpdatedAt,
+    PostField::TranslationOf,
+    PostField::Draft,
+    PostField::Unlisted,
+    PostField::PublishAt,
+];
+
+/// Keys out of `MATTER_FIELDS` such as `thumbnail`, in order of the frontmatter
+pub(super) fn get_extra_from_yaml(doc: &Yaml) -> LinkedHashMap<Yaml, Yaml> {
+    doc.as_hash()
+        .map(|hash| {
+            hash.iter()
+                .filter(|(k, _)| {
+                    !MATTER_FIELDS
+                        .iter()
+                        .any(|pf| k.as_str() == Some(pf.as_str()))
+                })
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Keys of json objects. Non-scalar keys of yaml are ignored.
+fn yaml_key_to_string(key: &Yaml) -> Option<String> {
+    match key {
+        Yaml::String(s) | Yaml::Real(s) => Some(s.to_owned()),
+        Yaml::Integer(i) => Some(i.to_string()),
+        Yaml::Boolean(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+pub(super) fn yaml_to_json(yaml: &Yaml) -> Value {
+    match yaml {
+        Yaml::String(s) => Value::String(s.to_owned()),
+        Yaml::Integer(i) => Value::Number((*i).into()),
+        Yaml::Real(s) => s
+            .parse()
+            .ok()
+            .and_then(Number::from_f64)
+            .map_or(Value::Null, Value::Number),
+        Yaml::Boolean(b) => Value::Bool(*b),
+        Yaml::Array(array) => Value::Array(array.iter().map(yaml_to_json).collect()),
+        Yaml::Hash(hash) => Value::Object(yaml_hash_to_json(hash)),
+        Yaml::Null | Yaml::Alias(_) | Yaml::BadValue => Value::Null,
+    }
+}
+
+pub(super) fn yaml_hash_to_json(hash: &LinkedHashMap<Yaml, Yaml>) -> Map<String, Value> {
+    hash.iter()
+        .filter_map(|(k, v)| yaml_key_to_string(k).map(|k| (k, yaml_to_json(v))))
+        .collect()
+}
+
+pub(super) fn json_to_yaml(value: &Value) -> Yaml {
+    match value {
+        Value::String(s) => Yaml::String(s.to_owned()),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => Yaml::Integer(i),
+            None => Yaml::Real(n.to_string()),
+        },
+        Value::Bool(b) => Yaml::Boolean(*b),
+        Value::Array(array) => Yaml::Array(array.iter().map(json_to_yaml).collect()),
+        Value::Object(map) => Yaml::Hash(json_map_to_yaml(map)),
+        Value::Null => Yaml::Null,
+    }
+}
+
+pub(super) fn json_map_to_yaml(map: &Map<String, Value>) -> LinkedHashMap<Yaml, Yaml> {
+    map.iter()
+        .map(|(k, v)| (Yaml::String(k.to_owned()), json_to_yaml(v)))
+        .collect()
+}
+
 pub(super) fn matter_to_yaml(matter: &FrontMatter) -> Yaml {
     fn insert_to_yamlmap<S: ToString>(k: S, v: String, lm: &mut LinkedHashMap<Yaml, Yaml>) {
         lm.insert(Yaml::String(k.to_string()), Yaml::String(v));
@@ -73,6 +168,10 @@ pub(super) fn matter_to_yaml(matter: &FrontMatter) -> Yaml {
     .into_iter()
     .collect();
 
+    let translation_of = matter
+        .translation_of()
+        .map(|uuid| (PostField::TranslationOf.as_str(), uuid));
+
     let opmap: LinkedHashMap<&str, Option<String>> = [
         (
             PostField::CreatedAt.as_str(),
@@ -89,7 +188,7 @@ pub(super) fn matter_to_yaml(matter: &FrontMatter) -> Yaml {
     let mut lm = LinkedHashMap::new();
 
     // Preserve insert order
-    for (k, v) in map.into_iter() {
+    for (k, v) in map.into_iter().chain(translation_of) {
         insert_to_yamlmap(k, v, &mut lm);
     }
 
@@ -107,7 +206,37 @@ pub(super) fn matter_to_yaml(matter: &FrontMatter) -> Yaml {
         }
     }
 
-    Yaml::Hash(lm)
+    let publication = matter.publication();
+    if let Some(publish_at) = publication.publish_at {
+        insert_to_yamlmap(
+            PostField::PublishAt.as_str(),
+            publish_at.to_string(),
+            &mut lm,
+        );
+    }
+    // only true flags are written not to add keys to every post
+    for (pf, flag) in [
+        (PostField::Draft, publication.draft),
+        (PostField::Unlisted, publication.unlisted),
+    ] {
+        if flag {
+            lm.insert(Yaml::String(pf.as_str().to_string()), Yaml::Boolean(true));
+        }
+    }
+
+    for (k, v) in matter.extra().iter() {
+        lm.insert(k.clone(), v.clone());
+    }
+
+    // keys of the parsed frontmatter keep their order, and new keys such as `updated_at` follow
+    let mut ordered: LinkedHashMap<Yaml, Yaml> = matter
+        .key_order()
+        .iter()
+        .filter_map(|k| lm.remove(k).map(|v| (k.clone(), v)))
+        .collect();
+    ordered.extend(lm);
+
+    Yaml::Hash(ordered)
 }
 
 #[cfg(test)]
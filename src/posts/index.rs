@@ -1,21 +1,26 @@
+use std::collections::BTreeSet;
+
 use anyhow::Result;
-use tantivy::{Index, IndexWriter};
+use futures::executor::block_on;
+use tantivy::{schema::Schema, Document, Index, IndexWriter};
 
 use super::utils::get_all_posts;
 use super::Post;
+use crate::config;
 use crate::io;
 use crate::posts::dump::dump_post;
-use crate::text_engine::query::put;
+use crate::text_engine::query::{get_all_by_uuid, stage, stage_delete};
 use crate::text_engine::schema::FieldGetter;
 
-fn prep_post_index(
+pub(super) fn prep_post_index(
     post: &mut Post,
-    fg: &FieldGetter,
-    index: &Index,
-    index_writer: &mut IndexWriter,
+    prev_doc: Option<&Document>,
+    schema: &Schema,
+    index_writer: &IndexWriter,
     skip_update_date: bool,
 ) -> Result<Option<String>> {
-    let doc = put(post, index, index_writer, skip_update_date)?;
+    let fg = FieldGetter::new(schema);
+    let doc = stage(post, prev_doc, schema, index_writer, skip_update_date)?;
     if let Some(doc) = doc {
         let updated_at =
             fg.get_date_with_format(&doc, crate::text_engine::schema::PostField::UpdatedAt)?;
@@ -28,35 +33,88 @@ fn prep_post_index(
     }
 }
 
-pub fn build(glob_pattern: &str, index: &Index, skip_update_date: bool) -> Result<()> {
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PrepSummary {
+    pub added: usize,
+    pub updated: usize,
+    pub deleted: usize,
+    pub unchanged: usize,
+}
+
+/// Index all posts matched by `glob_pattern`.
+/// All adds and deletes are staged first, and then committed at once,
+/// so that `prep` does not leave many small segments.
+pub fn build(glob_pattern: &str, index: &Index, skip_update_date: bool) -> Result<PrepSummary> {
     let schema = index.schema();
-    let fg = FieldGetter::new(&schema);
     let mut index_writer = index.writer(100_000_000)?;
-    let mut posts = get_all_posts(glob_pattern)?;
+    let mut posts = get_all_posts(glob_pattern, &config::get().frontmatter)?;
+    let indexed_docs = get_all_by_uuid(index)?;
+    let mut summary = PrepSummary::default();
 
     eprintln!("\n--- Start Preperation ---");
     eprintln!("- Find {} posts", posts.len());
-    let mut update_post_count = 0;
 
+    let mut found_uuids = BTreeSet::new();
+    let mut new_markdowns = Vec::new();
     for (path, post) in posts.iter_mut() {
+        let uuid = post.uuid();
+        let prev_doc = indexed_docs.get(&uuid);
         if let Some(new_markdown) =
-            prep_post_index(post, &fg, index, &mut index_writer, skip_update_date)?
+            prep_post_index(post, prev_doc, &schema, &index_writer, skip_update_date)?
         {
-            update_post_count += 1;
-            io::write_string(path, &new_markdown)?;
+            if prev_doc.is_some() {
+                summary.updated += 1;
+            } else {
+                summary.added += 1;
+            }
+            new_markdowns.push((path.to_owned(), new_markdown));
+        } else {
+            summary.unchanged += 1;
         }
+        found_uuids.insert(uuid);
+    }
+
+    // posts which exist in the index but not in the input directory are deleted or renamed
+    for uuid in indexed_docs
+        .keys()
+        .filter(|uuid| !found_uuids.contains(*uuid))
+    {
+        stage_delete(uuid, &schema, &index_writer);
+        summary.deleted += 1;
+    }
+
+    if summary.added + summary.updated + summary.deleted > 0 {
+        index_writer.commit()?;
+
+        let segment_ids = index.searchable_segment_ids()?;
+        if segment_ids.len() > 1 {
+            block_on(index_writer.merge(&segment_ids))?;
+        }
+    }
+    index_writer.wait_merging_threads()?;
+
+    // write back updated_at only after the index is committed
+    for (path, new_markdown) in new_markdowns.iter() {
+        io::write_string(path, new_markdown)?;
     }
 
-    eprintln!("- Update {} posts in this prepartion", update_post_count);
+    eprintln!(
+        "- Add {}, Update {}, Delete {}, Unchanged {} posts in this prepartion",
+        summary.added, summary.updated, summary.deleted, summary.unchanged
+    );
     eprintln!("-------- Finish! --------");
-    Ok(())
+    Ok(summary)
 }
 
 #[cfg(test)]
 mod test {
     use crate::{
+        datetime,
         test_utility::*,
-        text_engine::{query::search, schema::PostField},
+        text_engine::{
+            query::{get_by_uuid, get_page, parse_query},
+            schema::PostField,
+        },
     };
     use glob::glob;
     use tantivy::query::{AllQuery, Query};
@@ -75,6 +133,7 @@ mod test {
         let mut index_writer = index.writer(100_000_000)?;
         let schema = index.schema();
         let fg = FieldGetter::new(&schema);
+        let prev_doc = get_by_uuid(&posts[0].uuid(), &index)?;
 
         let target_body = posts[0].body_mut();
         *target_body = "<!-- comment --> abc".to_string();
@@ -82,12 +141,16 @@ mod test {
 
         // need skip update date because subtle change occurs
         let new_markdown =
-            prep_post_index(&mut posts[0], &fg, &index, &mut index_writer, true)?.unwrap();
+            prep_post_index(&mut posts[0], Some(&prev_doc), &schema, &index_writer, true)?.unwrap();
+        index_writer.commit()?;
 
         assert_eq!(old_markdown, new_markdown);
 
-        assert!(search("comment", vec![fg.get_field(PostField::Body)], 10, &index)?.is_empty());
-        assert!(!search("abc", vec![fg.get_field(PostField::Body)], 10, &index)?.is_empty());
+        for (query, hit) in [("comment", false), ("abc", true)] {
+            let q = parse_query(query, vec![fg.get_field(PostField::Body)], &index)?;
+            let (total, _) = get_page(&*q, &index, None, datetime::Order::Desc, 0, 10)?;
+            assert_eq!(total > 0, hit);
+        }
 
         Ok(())
     }
@@ -109,4 +172,46 @@ mod test {
         assert_eq!(docs.unwrap().len(), actual_files_count);
         Ok(())
     }
+
+    #[test]
+    fn test_build_delete_stale_posts() -> Result<()> {
+        let temp_dir = TempDir::new("test_build_delete_stale_posts")?;
+        let input_dir = temp_dir.path().join("posts");
+        std::fs::create_dir(&input_dir)?;
+        for name in ["a.md", "b.md", "c.md"] {
+            io::write_string(
+                &input_dir.join(name),
+                &crate::posts::template(&false, &None)?,
+            )?;
+        }
+        let glob_pattern = format!("{}/**/*.md", input_dir.display());
+
+        let schema = build_schema();
+        let index = read_or_build_index(schema, &temp_dir.path().join("index"), true)?;
+
+        let summary = build(&glob_pattern, &index, false)?;
+        assert_eq!(
+            summary,
+            PrepSummary {
+                added: 3,
+                ..Default::default()
+            }
+        );
+        assert_eq!(index.searchable_segment_ids()?.len(), 1);
+
+        std::fs::remove_file(input_dir.join("a.md"))?;
+        let summary = build(&glob_pattern, &index, false)?;
+        assert_eq!(
+            summary,
+            PrepSummary {
+                deleted: 1,
+                unchanged: 2,
+                ..Default::default()
+            }
+        );
+
+        let q: Box<dyn Query> = Box::new(AllQuery {});
+        assert_eq!(get_all(&q, &index, None)?.unwrap().len(), 2);
+        Ok(())
+    }
 }
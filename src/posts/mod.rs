@@ -6,10 +6,15 @@ mod extract_text;
 #[allow(clippy::module_inception)]
 mod posts;
 mod remove_comments;
+mod render_html;
 mod template;
+mod toc;
 pub mod utils;
+pub mod watch;
 
 pub use extract_text::*;
 pub use posts::*;
 pub use remove_comments::*;
+pub use render_html::*;
 pub use template::*;
+pub use toc::*;
@@ -6,42 +6,110 @@ use anyhow::{anyhow, Result};
 use tantivy::schema::*;
 
 use super::extract_text;
-use super::frontmatter::{split_frontmatter_and_content, FrontMatter};
+use super::frontmatter::{
+    parse_frontmatter, split_marked_frontmatter, FrontMatter, MarkedYaml, Publication, Violations,
+};
 use super::remove_comments;
+use super::render_html;
+use super::toc;
 
+use crate::config::{self, Config, TokenizerKind};
 use crate::datetime::{DateTimeFormat, DateTimeWithFormat};
 use crate::io::read_string;
-use crate::text_engine::schema::{FieldGetter, PostField};
+use crate::text_engine::schema::{category_facet, FieldGetter, PostField};
 
-#[cfg(test)]
-use strum_macros::{EnumCount, EnumIter};
-
-#[derive(Debug, Clone, PartialEq)]
-#[cfg_attr(test, derive(EnumIter, EnumCount))]
-pub enum Lang {
-    Ja,
-    En,
-}
+/// Language of the post as a canonical BCP-47 tag, which is one of the configured languages.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Lang(String);
 
 impl Lang {
+    /// all configured languages
+    pub fn all() -> Vec<Self> {
+        Self::all_in(config::get())
+    }
+
+    /// all languages of `config`
+    pub fn all_in(config: &Config) -> Vec<Self> {
+        config
+            .languages
+            .iter()
+            .map(|lang| Lang(lang.tag.clone()))
+            .collect()
+    }
+
+    pub fn default_lang() -> Self {
+        Lang(config::get().default_lang.clone())
+    }
+
     pub fn as_str(&self) -> &str {
-        match self {
-            Lang::Ja => "ja",
-            Lang::En => "en",
-        }
+        &self.0
     }
 
     pub fn from_str(lang: &str) -> Result<Self> {
-        match lang.to_lowercase().as_str() {
-            "ja" => Ok(Lang::Ja),
-            "en" => Ok(Lang::En),
-            _ => Err(anyhow!("Now support ja and en only!")),
+        Self::from_str_in(lang, config::get())
+    }
+
+    /// Language of `lang` if it is one of the languages of `config`
+    pub fn from_str_in(lang: &str, config: &Config) -> Result<Self> {
+        let tag = Self::canonicalize(lang)?;
+        if config.lang(&tag).is_none() {
+            let tags: Vec<&str> = config.languages.iter().map(|l| l.tag.as_str()).collect();
+            return Err(anyhow!(
+                "{} is not a configured language. Supported: {}",
+                lang,
+                tags.join(", ")
+            ));
         }
+        Ok(Lang(tag))
+    }
+
+    /// Canonicalize the case of BCP-47 tag, e.g. `zh_hant_tw` to `zh-Hant-TW`.
+    pub fn canonicalize(tag: &str) -> Result<String> {
+        let subtags: Vec<&str> = tag.split(['-', '_']).collect();
+        let is_valid = subtags
+            .iter()
+            .all(|s| (1..=8).contains(&s.len()) && s.chars().all(|c| c.is_ascii_alphanumeric()))
+            && subtags[0].len() >= 2
+            && subtags[0].chars().all(|c| c.is_ascii_alphabetic());
+        if !is_valid {
+            return Err(anyhow!("{} is not a valid BCP-47 language tag", tag));
+        }
+
+        let canonical: Vec<String> = subtags
+            .iter()
+            .enumerate()
+            .map(|(i, s)| {
+                if i > 0 && s.len() == 4 && s.chars().all(|c| c.is_ascii_alphabetic()) {
+                    // script
+                    s[..1].to_uppercase() + &s[1..].to_lowercase()
+                } else if i > 0 && (s.len() == 2 || s.chars().all(|c| c.is_ascii_digit())) {
+                    // region
+                    s.to_uppercase()
+                } else {
+                    s.to_lowercase()
+                }
+            })
+            .collect();
+        Ok(canonical.join("-"))
     }
 
     pub fn tokenizer_name(&self) -> String {
         "lang_".to_string() + self.as_str()
     }
+
+    /// Tokenizer of the language in `config`, or the simple tokenizer if it is not in `config`
+    pub fn tokenizer_kind(&self, config: &Config) -> TokenizerKind {
+        match config.lang(self.as_str()) {
+            Some(lang) => lang.tokenizer,
+            None => {
+                warn!(
+                    "{} is not a configured language. The simple tokenizer is used",
+                    self.as_str()
+                );
+                TokenizerKind::Simple
+            }
+        }
+    }
 }
 
 impl ToString for Lang {
@@ -184,18 +252,33 @@ impl Post {
     }
 
     pub fn from_path(path: &Path) -> Result<Self> {
+        Self::from_markdown(path, &read_string(path)?)
+    }
+
+    /// Post of `markdown_text` read from `path`
+    pub fn from_markdown(path: &Path, markdown_text: &str) -> Result<Self> {
+        Ok(Self::from_marked_markdown(path, markdown_text)?.0)
+    }
+
+    /// Post with its loaded frontmatter, to check the frontmatter by other rules
+    pub fn from_marked_markdown(path: &Path, markdown_text: &str) -> Result<(Self, MarkedYaml)> {
         let slug = path_to_slug(path);
 
-        let markdown_text = read_string(&path).unwrap();
-        let (frontmatter, body) = split_frontmatter_and_content(&markdown_text);
-        let matter = frontmatter.unwrap_or_else(|| panic!("{:?} does not have frontmatter.", path));
-        let raw_text = Some(extract_text(&body)?);
-        Ok(Self {
+        let at_path = |e: anyhow::Error| match e.downcast_ref::<Violations>() {
+            Some(violations) => anyhow!(violations.at(path)),
+            None => e.context(format!("{:?} has invalid frontmatter.", path)),
+        };
+        let (yaml, body) = split_marked_frontmatter(markdown_text).map_err(at_path)?;
+        let yaml = yaml.ok_or_else(|| anyhow!("{:?} does not have frontmatter.", path))?;
+        let matter = parse_frontmatter(&yaml).map_err(at_path)?;
+        let raw_text = Some(extract_text(body)?);
+        let post = Self {
             slug,
             matter,
             body: body.to_string(),
             raw_text,
-        })
+        };
+        Ok((post, yaml))
     }
 
     pub fn from_doc(doc: &Document, schema: &Schema) -> Result<Self> {
@@ -209,6 +292,14 @@ impl Post {
         let lang = fb.get_text(doc, PostField::Lang)?;
         let category = fb.get_text(doc, PostField::Category)?;
         let tags = fb.get_text(doc, PostField::Tags)?;
+        let translation_of = fb.get_text(doc, PostField::TranslationOf)?;
+        let publication = Publication {
+            draft: fb.get_text(doc, PostField::Draft)? == "true",
+            publish_at: fb
+                .get_optional_date(doc, PostField::PublishAt)
+                .map(|date| DateTimeWithFormat::new(date, DateTimeFormat::RFC3339)),
+            unlisted: fb.get_text(doc, PostField::Unlisted)? == "true",
+        };
 
         let created_at = fb.get_date(doc, PostField::CreatedAt)?;
         let updated_at = fb.get_date(doc, PostField::UpdatedAt)?;
@@ -235,7 +326,10 @@ impl Post {
                 tags,
                 Some(DateTimeWithFormat::new(created_at, created_at_format)),
                 Some(DateTimeWithFormat::new(updated_at, updated_at_format)),
-            ),
+            )
+            .with_translation_of(Some(translation_of).filter(|uuid| !uuid.is_empty()))
+            .with_publication(publication)
+            .with_extra_json(&fb.get_extra(doc)?),
         })
     }
 
@@ -247,6 +341,7 @@ impl Post {
     ) -> Document {
         let fb = FieldGetter::new(schema);
         let mut doc = Document::new();
+        let publication = self.matter.publication();
 
         [
             (PostField::Uuid, self.uuid()),
@@ -255,13 +350,34 @@ impl Post {
             (PostField::Description, self.matter.description()),
             (PostField::Lang, self.lang().as_str().to_string()),
             (PostField::Category, self.matter.category()),
+            (
+                PostField::TranslationOf,
+                self.matter.translation_of().unwrap_or_default(),
+            ),
+            (PostField::Draft, publication.draft.to_string()),
+            (PostField::Unlisted, publication.unlisted.to_string()),
             (PostField::CreatedAtFormat, created_at.format().to_string()),
             (PostField::UpdatedAtFormat, updated_at.format().to_string()),
         ]
         .into_iter()
         .for_each(|(pf, text)| doc.add_text(fb.get_field(pf), text));
 
-        doc.add_text(fb.get_field(PostField::Body), remove_comments(&self.body()));
+        let category = category_facet(&self.matter.category());
+        if !category.is_root() {
+            doc.add_facet(fb.get_field(PostField::CategoryFacet), category);
+        }
+
+        let body = remove_comments(&self.body());
+        doc.add_text(fb.get_field(PostField::Html), render_html(&body));
+        doc.add_text(
+            fb.get_field(PostField::Toc),
+            serde_json::to_string(&toc(&body)).unwrap(),
+        );
+        doc.add_text(fb.get_field(PostField::Body), body);
+        doc.add_text(
+            fb.get_field(PostField::Extra),
+            serde_json::Value::Object(self.matter.extra_json()).to_string(),
+        );
 
         if let Some(raw_text) = self.raw_text() {
             let body_raw_text = extract_text(&self.body).unwrap();
@@ -275,6 +391,15 @@ impl Post {
             doc.add_text(fb.get_field(PostField::RawText), raw_text);
         }
 
+        // index the text with the tokenizer of the language of the post
+        let lang = self.lang();
+        for pf in PostField::lang_fields() {
+            if let Some(text) = doc.get_first(fb.get_field(pf)).and_then(|v| v.text()) {
+                let text = text.to_string();
+                doc.add_text(fb.get_lang_field(pf, &lang), text);
+            }
+        }
+
         let tags = fb.get_field(PostField::Tags);
 
         let tag_text = match self.matter.tags() {
@@ -286,6 +411,9 @@ impl Post {
 
         doc.add_date(fb.get_field(PostField::CreatedAt), &created_at.datetime());
         doc.add_date(fb.get_field(PostField::UpdatedAt), &updated_at.datetime());
+        if let Some(publish_at) = publication.publish_at {
+            doc.add_date(fb.get_field(PostField::PublishAt), &publish_at.datetime());
+        }
 
         doc
     }
@@ -0,0 +1,69 @@
+use pulldown_cmark::{html, Event, Options, Parser, Tag};
+
+use super::headings;
+
+/// Markdown extensions enabled in the rendered html
+pub fn render_options() -> Options {
+    Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+}
+
+/// Render markdown into html with tables, footnotes, strikethrough and task lists.
+/// Headings have the same ids as the table of contents.
+pub fn render_html(markdown_text: &str) -> String {
+    let ids: Vec<String> = headings(markdown_text)
+        .into_iter()
+        .map(|(_, _, id)| id)
+        .collect();
+    let mut ids = ids.iter();
+
+    let parser = Parser::new_ext(markdown_text, render_options()).map(|e| match e {
+        Event::Start(Tag::Heading(level, _, classes)) => Event::Start(Tag::Heading(
+            level,
+            ids.next().map(|id| id.as_str()),
+            classes,
+        )),
+        _ => e,
+    });
+    let mut html_text = String::new();
+    html::push_html(&mut html_text, parser);
+    html_text
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_render_html() {
+        let markdown_text = r#"
+| a | b |
+|---|---|
+| 1 | 2 |
+
+~~old~~ text[^1]
+
+- [x] done
+- [ ] todo
+
+[^1]: footnote
+"#;
+        let html_text = render_html(markdown_text);
+        assert!(html_text.contains("<table>"));
+        assert!(html_text.contains("<td>1</td>"));
+        assert!(html_text.contains("<del>old</del>"));
+        assert!(html_text.contains("<input disabled=\"\" type=\"checkbox\" checked=\"\"/>"));
+        assert!(html_text.contains("class=\"footnote-definition\""));
+    }
+
+    #[test]
+    fn test_render_html_heading_ids() {
+        let html_text = render_html("## Hello World\n\n## Hello World\n");
+        assert_eq!(
+            html_text,
+            "<h2 id=\"hello-world\">Hello World</h2>\n<h2 id=\"hello-world-1\">Hello World</h2>\n"
+        );
+    }
+}
@@ -27,7 +27,7 @@ pub fn template(with_date: &bool, datetime_format: &Option<String>) -> Result<St
         "",
         "",
         "",
-        Lang::Ja,
+        Lang::default_lang(),
         None,
         created_at,
         updated_at,
@@ -0,0 +1,126 @@
+use std::collections::HashSet;
+
+use pulldown_cmark::{Event, Parser, Tag};
+use serde::{Deserialize, Serialize};
+
+use super::render_options;
+
+/// Entry of table of contents. `id` is the anchor of the heading in the rendered html.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TocEntry {
+    pub level: u32,
+    pub text: String,
+    pub id: String,
+    pub children: Vec<TocEntry>,
+}
+
+/// Make an anchor id from heading text.
+/// Alphanumeric characters including non-ascii ones are kept, and whitespaces become `-`.
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.trim().chars() {
+        if c.is_alphanumeric() || c == '_' {
+            slug.extend(c.to_lowercase());
+        } else if (c.is_whitespace() || c == '-') && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_matches('-').to_string()
+}
+
+/// Level, text and unique anchor id of each heading in order of the document.
+pub fn headings(markdown_text: &str) -> Vec<(u32, String, String)> {
+    let mut headings = Vec::new();
+    let mut used_ids = HashSet::new();
+    let mut current: Option<(u32, String)> = None;
+
+    for e in Parser::new_ext(markdown_text, render_options()) {
+        match e {
+            Event::Start(Tag::Heading(level, _, _)) => {
+                current = Some((level as u32, String::new()))
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some((_, heading_text)) = current.as_mut() {
+                    heading_text.push_str(&text);
+                }
+            }
+            Event::End(Tag::Heading(..)) => {
+                if let Some((level, text)) = current.take() {
+                    let base = match slugify(&text) {
+                        s if s.is_empty() => "section".to_string(),
+                        s => s,
+                    };
+                    // duplicated headings get suffixes as `intro`, `intro-1`, ...
+                    let mut id = base.clone();
+                    let mut n = 0;
+                    while used_ids.contains(&id) {
+                        n += 1;
+                        id = format!("{}-{}", base, n);
+                    }
+                    used_ids.insert(id.clone());
+                    headings.push((level, text, id));
+                }
+            }
+            _ => continue,
+        }
+    }
+
+    headings
+}
+
+/// Nested table of contents. A heading is a child of the previous heading with a lower level.
+pub fn toc(markdown_text: &str) -> Vec<TocEntry> {
+    fn push(entries: &mut Vec<TocEntry>, entry: TocEntry) {
+        match entries.last_mut() {
+            Some(last) if last.level < entry.level => push(&mut last.children, entry),
+            _ => entries.push(entry),
+        }
+    }
+
+    let mut entries = Vec::new();
+    for (level, text, id) in headings(markdown_text) {
+        push(
+            &mut entries,
+            TocEntry {
+                level,
+                text,
+                id,
+                children: Vec::new(),
+            },
+        );
+    }
+    entries
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Hello, World!"), "hello-world");
+        assert_eq!(slugify("  `async` -  Rust "), "async-rust");
+        assert_eq!(slugify("日本語 の見出し"), "日本語-の見出し");
+    }
+
+    #[test]
+    fn test_toc() {
+        let markdown_text =
+            "# Title\n\n## Intro\n\n### Detail `code`\n\n## Intro\n\ntext\n\n# !!!\n";
+        let toc = toc(markdown_text);
+
+        assert_eq!(toc.len(), 2);
+        assert_eq!(toc[0].id, "title");
+        assert_eq!(
+            toc[0]
+                .children
+                .iter()
+                .map(|e| e.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["intro", "intro-1"]
+        );
+        assert_eq!(toc[0].children[0].children[0].text, "Detail code");
+        assert_eq!(toc[0].children[0].children[0].level, 3);
+        assert_eq!(toc[1].id, "section");
+    }
+}
@@ -1,33 +1,62 @@
+use crate::config::FrontMatterConfig;
+use crate::io::read_string;
 use crate::posts::Post;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use glob::glob;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-pub fn get_all_posts(glob_pattern: &str) -> Result<Vec<(PathBuf, Post)>> {
-    let posts = glob(glob_pattern)?
+/// Read the post and check its frontmatter by `rules`.
+/// Violations of the rules are warned, or make an error if the rules are strict.
+pub fn read_post(path: &Path, rules: &FrontMatterConfig) -> Result<Post> {
+    let (post, yaml) = Post::from_marked_markdown(path, &read_string(path)?)?;
+
+    let violations: Vec<String> = yaml
+        .check_rules(rules)
         .into_iter()
-        .filter_map(|path| path.ok())
-        .filter_map(|path| {
-            // should be /path/to/filename.md
-            let post = Post::from_path(&path);
-            if let Ok(post) = post {
-                Some((path, post))
-            } else {
-                error!(
-                    "Error in {:?}. Maybe this file has invalid frontmatter. Skipping this file.",
-                    path
-                );
-                None
-            }
-        })
+        .map(|v| v.at(path))
         .collect();
+    if violations.is_empty() {
+        Ok(post)
+    } else if rules.strict {
+        Err(anyhow!(violations.join("\n")))
+    } else {
+        violations.iter().for_each(|v| warn!("{}", v));
+        Ok(post)
+    }
+}
+
+/// Read all posts matched by `glob_pattern`.
+/// Invalid posts are skipped with errors, or fail all if `rules` are strict.
+pub fn get_all_posts(
+    glob_pattern: &str,
+    rules: &FrontMatterConfig,
+) -> Result<Vec<(PathBuf, Post)>> {
+    let mut posts = Vec::new();
+    let mut errors = Vec::new();
+    for path in glob(glob_pattern)?.filter_map(|path| path.ok()) {
+        // should be /path/to/filename.md
+        match read_post(&path, rules) {
+            Ok(post) => posts.push((path, post)),
+            Err(e) if rules.strict => errors.push(format!("{:#}", e)),
+            Err(e) => error!("Error in {:?}: {:#}. Skipping this file.", path, e),
+        }
+    }
 
-    Ok(posts)
+    if errors.is_empty() {
+        Ok(posts)
+    } else {
+        Err(anyhow!(
+            "{} posts have invalid frontmatter\n{}",
+            errors.len(),
+            errors.join("\n")
+        ))
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::config::Config;
     use crate::posts::template;
     use std::fs;
     use std::io::Write;
@@ -58,10 +87,49 @@ mod test {
             .flatten()
             .filter(|x| x.ends_with(".md"))
             .count();
-        let actual_files_count = get_all_posts(&format!("{}/**/*.md", temp_dir.path().display()))
-            .unwrap()
-            .len();
+        let actual_files_count = get_all_posts(
+            &format!("{}/**/*.md", temp_dir.path().display()),
+            &FrontMatterConfig::default(),
+        )
+        .unwrap()
+        .len();
 
         assert_eq!(expect_files_count, actual_files_count);
     }
+
+    #[test]
+    fn test_get_all_posts_with_rules() {
+        let temp_dir = tempdir::TempDir::new("test_get_all_posts_with_rules").unwrap();
+        let glob_pattern = format!("{}/*.md", temp_dir.path().display());
+        let mut f = fs::File::create(temp_dir.path().join("a.md")).unwrap();
+        write!(
+            f,
+            "---\nuuid: a\ntitle: A\ndescription: A\ncategory: travel\n---\nbody\n"
+        )
+        .unwrap();
+        let mut f = fs::File::create(temp_dir.path().join("b.md")).unwrap();
+        write!(
+            f,
+            "---\nuuid: b\ntitle: B\ncategory: life\ntags:\n  - [b]\n---\n"
+        )
+        .unwrap();
+
+        let mut rules = Config::from_yaml_str(
+            "frontmatter:\n  fields:\n    category:\n      enum: [programming, life]\n",
+        )
+        .unwrap()
+        .frontmatter;
+        // violations are warned and invalid posts are skipped
+        let posts = get_all_posts(&glob_pattern, &rules).unwrap();
+        assert_eq!(posts.len(), 1);
+
+        rules.strict = true;
+        let message = format!("{:#}", get_all_posts(&glob_pattern, &rules).unwrap_err());
+        assert!(message.starts_with("2 posts have invalid frontmatter\n"));
+        assert!(
+            message.contains("a.md:5: category must be one of programming, life, but is travel")
+        );
+        assert!(message.contains("b.md:1: description is required"));
+        assert!(message.contains("b.md:5: tags must be a list of strings or integers"));
+    }
 }
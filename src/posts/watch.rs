@@ -0,0 +1,298 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread::sleep;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use glob::glob;
+use tantivy::Index;
+
+use super::index::{prep_post_index, PrepSummary};
+use super::utils::read_post;
+use super::Post;
+use crate::config;
+use crate::io;
+use crate::text_engine::query::{get_by_uuid, stage_delete};
+
+#[derive(Debug, Clone)]
+struct WatchedFile {
+    modified: SystemTime,
+    uuid: Option<String>,
+    /// markdown written back by the watcher itself.
+    written: Option<String>,
+}
+
+/// Poll the markdown files matched by `glob_pattern` and reindex the changed files.
+pub struct PostWatcher {
+    glob_pattern: String,
+    skip_update_date: bool,
+    files: HashMap<PathBuf, WatchedFile>,
+}
+
+fn modified(path: &Path) -> Result<SystemTime> {
+    Ok(fs::metadata(path)?.modified()?)
+}
+
+impl PostWatcher {
+    /// Start watching from the current state of the files.
+    /// The files should have been indexed by `posts::index::build` already.
+    pub fn new(glob_pattern: &str, skip_update_date: bool) -> Result<Self> {
+        let mut files = HashMap::new();
+        for path in glob(glob_pattern)?.filter_map(|path| path.ok()) {
+            let uuid = Post::from_path(&path).ok().map(|post| post.uuid());
+            let file = WatchedFile {
+                modified: modified(&path)?,
+                uuid,
+                written: None,
+            };
+            files.insert(path, file);
+        }
+
+        Ok(Self {
+            glob_pattern: glob_pattern.to_string(),
+            skip_update_date,
+            files,
+        })
+    }
+
+    fn changed_paths(&self) -> Result<(Vec<PathBuf>, Vec<PathBuf>)> {
+        let mut changed = Vec::new();
+        let mut found = BTreeSet::new();
+        for path in glob(&self.glob_pattern)?.filter_map(|path| path.ok()) {
+            let is_changed = match self.files.get(&path) {
+                // the file removed after glob is also read and skipped
+                Some(file) => modified(&path).ok() != Some(file.modified),
+                None => true,
+            };
+            if is_changed {
+                changed.push(path.clone());
+            }
+            found.insert(path);
+        }
+
+        let removed = self
+            .files
+            .keys()
+            .filter(|path| !found.contains(*path))
+            .cloned()
+            .collect();
+
+        Ok((changed, removed))
+    }
+
+    /// Reindex files changed since the last poll, and delete posts of removed files.
+    /// The files are recorded only after the commit, so they are polled again on errors.
+    pub fn poll(&mut self, index: &Index) -> Result<PrepSummary> {
+        let mut summary = PrepSummary::default();
+        let (changed, removed) = self.changed_paths()?;
+        if changed.is_empty() && removed.is_empty() {
+            return Ok(summary);
+        }
+
+        let schema = index.schema();
+        let mut index_writer = index.writer(100_000_000)?;
+        let mut stale_uuids = BTreeSet::new();
+        let mut new_markdowns = Vec::new();
+        let mut files = self.files.clone();
+
+        for path in removed.iter() {
+            if let Some(uuid) = files.remove(path).and_then(|file| file.uuid) {
+                stale_uuids.insert(uuid);
+            }
+        }
+
+        for path in changed.into_iter() {
+            // the file is left to the next poll if it is being replaced, e.g. by atomic save
+            let text = match io::read_string(&path) {
+                Ok(text) => text,
+                Err(e) => {
+                    error!("Error in reading {:?}: {:?}", path, e);
+                    continue;
+                }
+            };
+            let prev_file = files.remove(&path);
+
+            // skip frontmatter write-back by the watcher itself, not to reindex endlessly
+            if let Some(prev_file) = prev_file.as_ref() {
+                if prev_file.written.as_deref() == Some(text.as_str()) {
+                    files.insert(
+                        path.clone(),
+                        WatchedFile {
+                            modified: modified(&path)?,
+                            uuid: prev_file.uuid.clone(),
+                            written: None,
+                        },
+                    );
+                    continue;
+                }
+            }
+
+            let prev_uuid = prev_file.and_then(|file| file.uuid);
+            let mut post = match read_post(&path, &config::get().frontmatter) {
+                Ok(post) => post,
+                Err(e) => {
+                    error!("Error in {:?}: {:?}. Skipping this file.", path, e);
+                    files.insert(
+                        path.clone(),
+                        WatchedFile {
+                            modified: modified(&path)?,
+                            uuid: prev_uuid,
+                            written: None,
+                        },
+                    );
+                    continue;
+                }
+            };
+
+            let uuid = post.uuid();
+            if let Some(prev_uuid) = prev_uuid {
+                if prev_uuid != uuid {
+                    stale_uuids.insert(prev_uuid);
+                }
+            }
+
+            let prev_doc = get_by_uuid(&uuid, index).ok();
+            match prep_post_index(
+                &mut post,
+                prev_doc.as_ref(),
+                &schema,
+                &index_writer,
+                self.skip_update_date,
+            )? {
+                Some(new_markdown) => {
+                    if prev_doc.is_some() {
+                        summary.updated += 1;
+                    } else {
+                        summary.added += 1;
+                    }
+                    new_markdowns.push((path.clone(), new_markdown));
+                }
+                None => summary.unchanged += 1,
+            }
+
+            files.insert(
+                path.clone(),
+                WatchedFile {
+                    modified: modified(&path)?,
+                    uuid: Some(uuid),
+                    written: None,
+                },
+            );
+        }
+
+        // the post may be moved to another file
+        let indexed_uuids: BTreeSet<String> = files
+            .values()
+            .filter_map(|file| file.uuid.clone())
+            .collect();
+        for uuid in stale_uuids.difference(&indexed_uuids) {
+            stage_delete(uuid, &schema, &index_writer);
+            summary.deleted += 1;
+        }
+
+        index_writer.commit()?;
+        self.files = files;
+
+        for (path, new_markdown) in new_markdowns.into_iter() {
+            io::write_string(&path, &new_markdown)?;
+            if let Some(file) = self.files.get_mut(&path) {
+                file.modified = modified(&path)?;
+                file.written = Some(new_markdown);
+            }
+        }
+
+        Ok(summary)
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+pub fn watch(
+    glob_pattern: &str,
+    index: &Index,
+    skip_update_date: bool,
+    interval: Duration,
+) -> Result<()> {
+    let mut watcher = PostWatcher::new(glob_pattern, skip_update_date)?;
+    eprintln!("\n--- Start Watching {} ---", glob_pattern);
+
+    loop {
+        sleep(interval);
+        // files may be replaced or removed while polling, so errors do not stop watching
+        match watcher.poll(index) {
+            Ok(summary) if summary != PrepSummary::default() => eprintln!(
+                "- Add {}, Update {}, Delete {}, Unchanged {} posts",
+                summary.added, summary.updated, summary.deleted, summary.unchanged
+            ),
+            Ok(_) => (),
+            Err(e) => error!("Error in watching {}: {:?}", glob_pattern, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::posts::{index::build, template};
+    use crate::text_engine::{index::read_or_build_index, schema::build_schema};
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_poll() -> Result<()> {
+        let temp_dir = TempDir::new("test_watch_poll")?;
+        let input_dir = temp_dir.path().join("posts");
+        fs::create_dir(&input_dir)?;
+        for name in ["a.md", "b.md"] {
+            io::write_string(&input_dir.join(name), &template(&false, &None)?)?;
+        }
+        let glob_pattern = format!("{}/**/*.md", input_dir.display());
+
+        let index = read_or_build_index(build_schema(), &temp_dir.path().join("index"), true)?;
+        build(&glob_pattern, &index, false)?;
+
+        let mut watcher = PostWatcher::new(&glob_pattern, false)?;
+        assert_eq!(watcher.poll(&index)?, PrepSummary::default());
+
+        // add new post
+        let c_path = input_dir.join("c.md");
+        io::write_string(&c_path, &template(&false, &None)?)?;
+        let summary = watcher.poll(&index)?;
+        assert_eq!(summary.added, 1);
+        let c_uuid = Post::from_path(&c_path)?.uuid();
+        assert!(get_by_uuid(&c_uuid, &index).is_ok());
+
+        // write-back of the frontmatter does not trigger reindex
+        assert_eq!(watcher.poll(&index)?, PrepSummary::default());
+
+        // update post
+        let mut text = io::read_string(&c_path)?;
+        text.push_str("\nupdated\n");
+        io::write_string(&c_path, &text)?;
+        let summary = watcher.poll(&index)?;
+        assert_eq!(summary.updated, 1);
+        assert_eq!(watcher.poll(&index)?, PrepSummary::default());
+
+        // remove post
+        fs::remove_file(&c_path)?;
+        let summary = watcher.poll(&index)?;
+        assert_eq!(summary.deleted, 1);
+        assert!(get_by_uuid(&c_uuid, &index).is_err());
+
+        // rename post
+        let a_uuid = Post::from_path(&input_dir.join("a.md"))?.uuid();
+        fs::rename(input_dir.join("a.md"), input_dir.join("d.md"))?;
+        let summary = watcher.poll(&index)?;
+        assert_eq!(summary.deleted, 0);
+        assert!(get_by_uuid(&a_uuid, &index).is_ok());
+
+        // unreadable files are skipped without stopping the watcher
+        fs::create_dir(input_dir.join("e.md"))?;
+        assert_eq!(watcher.poll(&index)?, PrepSummary::default());
+        let e_text = template(&false, &None)?;
+        fs::remove_dir(input_dir.join("e.md"))?;
+        io::write_string(&input_dir.join("e.md"), &e_text)?;
+        assert_eq!(watcher.poll(&index)?.added, 1);
+
+        Ok(())
+    }
+}
@@ -1,16 +1,152 @@
 use actix_cors::Cors;
-use actix_web::{middleware, web, App, HttpServer};
+use actix_web::{middleware, web, App, HttpRequest, HttpServer};
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use std::path::PathBuf;
+use std::sync::RwLock;
+use tantivy::{
+    directory::{Directory, WatchCallback, WatchHandle},
+    Index,
+};
 
 use crate::text_engine::{
-    index::read_or_build_index, query::get_tags_and_categories, schema::build_schema,
+    index::read_or_build_index,
+    query::{get_tags_and_categories, next_publish_at},
+    schema::build_schema,
 };
 
 mod route;
 
-pub struct CategoryList(Vec<String>);
-pub struct TagList(Vec<String>);
+/// Names of tags or categories at the last reload of the index.
+/// It is outdated at `expires_at`, the next `publish_at` of the scheduled posts.
+struct Snapshot {
+    names: Vec<String>,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+impl Snapshot {
+    fn get(&self) -> Option<Vec<String>> {
+        match self.expires_at {
+            Some(expires_at) if expires_at <= Utc::now() => None,
+            _ => Some(self.names.clone()),
+        }
+    }
+}
+
+pub struct CategoryList(RwLock<Snapshot>);
+pub struct TagList(RwLock<Snapshot>);
+
+impl CategoryList {
+    pub fn new(categories: Vec<String>) -> Self {
+        Self(RwLock::new(Snapshot {
+            names: categories,
+            expires_at: None,
+        }))
+    }
+
+    pub fn with_expires_at(self, expires_at: Option<DateTime<Utc>>) -> Self {
+        self.0.write().unwrap().expires_at = expires_at;
+        self
+    }
+
+    /// `None` if the categories are outdated by the scheduled posts
+    pub fn get(&self) -> Option<Vec<String>> {
+        self.0.read().unwrap().get()
+    }
+}
+
+impl TagList {
+    pub fn new(tags: Vec<String>) -> Self {
+        Self(RwLock::new(Snapshot {
+            names: tags,
+            expires_at: None,
+        }))
+    }
+
+    pub fn with_expires_at(self, expires_at: Option<DateTime<Utc>>) -> Self {
+        self.0.write().unwrap().expires_at = expires_at;
+        self
+    }
+
+    /// `None` if the tags are outdated by the scheduled posts
+    pub fn get(&self) -> Option<Vec<String>> {
+        self.0.read().unwrap().get()
+    }
+}
+
+/// Preview of drafts and scheduled posts.
+/// All requests are previews with `--preview`, or the requests with the token of `--preview-token`.
+pub struct Preview {
+    enabled: bool,
+    token: Option<String>,
+}
+
+/// Header of the preview token.
+/// The token is not accepted in the query, which is written to the access log.
+const PREVIEW_TOKEN_HEADER: &str = "X-Preview-Token";
+
+/// Compare without returning early, not to leak the token by the response time
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+impl Preview {
+    pub fn new(enabled: bool, token: Option<String>) -> Self {
+        Self { enabled, token }
+    }
+
+    pub fn allows(&self, req: &HttpRequest) -> bool {
+        if self.enabled {
+            return true;
+        }
+        let token = match self.token.as_deref() {
+            Some(token) if !token.is_empty() => token,
+            _ => return false,
+        };
+
+        req.headers()
+            .get(PREVIEW_TOKEN_HEADER)
+            .is_some_and(|value| constant_time_eq(value.as_bytes(), token.as_bytes()))
+    }
+}
+
+/// Recompute tags and categories from the current commit of the index.
+fn reload_tags_and_categories(
+    index: &Index,
+    tags: &TagList,
+    categories: &CategoryList,
+) -> Result<()> {
+    let (new_tags, new_categories) = get_tags_and_categories(index)?;
+    let expires_at = next_publish_at(index)?;
+    *tags.0.write().unwrap() = Snapshot {
+        names: new_tags,
+        expires_at,
+    };
+    *categories.0.write().unwrap() = Snapshot {
+        names: new_categories,
+        expires_at,
+    };
+    Ok(())
+}
+
+/// Watch commits to the index, e.g. by `prep`, and reload tags and categories.
+/// Posts do not need to be reloaded because each request opens a new reader.
+/// The returned handle must be kept alive while watching.
+fn watch_index(
+    index: &Index,
+    tags: web::Data<TagList>,
+    categories: web::Data<CategoryList>,
+) -> Result<WatchHandle> {
+    let watched_index = index.clone();
+    let callback = WatchCallback::new(move || {
+        match reload_tags_and_categories(&watched_index, &tags, &categories) {
+            Ok(()) => info!("reload tags and categories"),
+            Err(e) => error!("Error in reloading tags and categories: {:?}", e),
+        }
+    });
+
+    Ok(index.directory().watch(callback)?)
+}
 
 #[cfg(not(tarpaulin_include))]
 #[actix_web::main]
@@ -20,6 +156,7 @@ pub async fn main(
     index_dir: PathBuf,
     static_dir: PathBuf,
     _cors_origin: Option<String>,
+    preview: Preview,
 ) -> Result<()> {
     eprintln!(
         "Index Dir: {}, Static Dir: {}",
@@ -36,42 +173,66 @@ pub async fn main(
     let schema = build_schema();
     let index = read_or_build_index(schema, &index_dir, false)?;
     let (tags, categories) = get_tags_and_categories(&index)?;
+    let expires_at = next_publish_at(&index)?;
+    let tags = web::Data::new(TagList::new(tags).with_expires_at(expires_at));
+    let categories = web::Data::new(CategoryList::new(categories).with_expires_at(expires_at));
+    let _watch_handle = watch_index(&index, tags.clone(), categories.clone())?;
+    let preview = web::Data::new(preview);
+
     HttpServer::new(move || {
         if let Some(cors_origin) = _cors_origin.as_ref() {
             App::new()
                 .app_data(web::Data::new(index.clone()))
-                .app_data(web::Data::new(CategoryList(categories.clone())))
-                .app_data(web::Data::new(TagList(tags.clone())))
+                .app_data(categories.clone())
+                .app_data(tags.clone())
+                .app_data(preview.clone())
                 .wrap(middleware::Logger::default())
                 .wrap(middleware::Compress::default())
                 .wrap(Cors::default().allowed_origin(cors_origin))
                 .service(route::openapi::get_openapi_schema)
                 .service(route::posts::get_post_by_id)
+                .service(route::posts::get_related_posts)
+                .service(route::posts::get_adjacent_posts_by_id)
+                .service(route::posts::get_adjacent_posts_by_slug)
                 .service(route::posts::get_posts)
                 .service(route::posts::count_posts)
                 .service(route::posts::get_post_by_slug_and_lang)
                 .service(route::search::search_posts)
+                .service(route::archive::get_archive_list)
+                .service(route::feed::get_rss)
+                .service(route::feed::get_atom)
+                .service(route::sitemap::get_sitemap)
                 .service(route::hello)
                 .service(route::tag_list)
                 .service(route::category_list)
+                .service(route::category_tree_list)
                 .service(actix_files::Files::new(static_uri, &static_dir).show_files_listing())
         } else {
             App::new()
                 .app_data(web::Data::new(index.clone()))
-                .app_data(web::Data::new(CategoryList(categories.clone())))
-                .app_data(web::Data::new(TagList(tags.clone())))
+                .app_data(categories.clone())
+                .app_data(tags.clone())
+                .app_data(preview.clone())
                 .wrap(middleware::Logger::default())
                 .wrap(middleware::Compress::default())
                 .wrap(Cors::default())
                 .service(route::openapi::get_openapi_schema)
                 .service(route::posts::get_post_by_id)
+                .service(route::posts::get_related_posts)
+                .service(route::posts::get_adjacent_posts_by_id)
+                .service(route::posts::get_adjacent_posts_by_slug)
                 .service(route::posts::get_posts)
                 .service(route::posts::count_posts)
                 .service(route::posts::get_post_by_slug_and_lang)
                 .service(route::search::search_posts)
+                .service(route::archive::get_archive_list)
+                .service(route::feed::get_rss)
+                .service(route::feed::get_atom)
+                .service(route::sitemap::get_sitemap)
                 .service(route::hello)
                 .service(route::tag_list)
                 .service(route::category_list)
+                .service(route::category_tree_list)
                 .service(actix_files::Files::new(static_uri, &static_dir).show_files_listing())
         }
     })
@@ -80,3 +241,60 @@ pub async fn main(
     .await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utility::*;
+    use actix_web::test::TestRequest;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_preview() {
+        let req = TestRequest::default().to_http_request();
+        assert!(Preview::new(true, None).allows(&req));
+        assert!(!Preview::new(false, None).allows(&req));
+
+        let preview = Preview::new(false, Some("secret".to_string()));
+        assert!(!preview.allows(&req));
+        let req = TestRequest::default()
+            .insert_header((PREVIEW_TOKEN_HEADER, "secret"))
+            .to_http_request();
+        assert!(preview.allows(&req));
+        let req = TestRequest::default()
+            .insert_header((PREVIEW_TOKEN_HEADER, "secrets"))
+            .to_http_request();
+        assert!(!preview.allows(&req));
+        // the token in the query would be written to the access log
+        let req = TestRequest::with_uri("/posts?tag=a&preview_token=secret").to_http_request();
+        assert!(!preview.allows(&req));
+        let req = TestRequest::default()
+            .insert_header((PREVIEW_TOKEN_HEADER, ""))
+            .to_http_request();
+        assert!(!Preview::new(false, Some(String::new())).allows(&req));
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+    }
+
+    #[test]
+    fn test_reload_tags_and_categories() -> Result<()> {
+        let temp_dir = TempDir::new("test_reload_tags_and_categories")?;
+        let (_, index) = build_random_posts_index(5, temp_dir.path())?;
+        let tags = TagList::new(Vec::new());
+        let categories = CategoryList::new(Vec::new());
+
+        reload_tags_and_categories(&index, &tags, &categories)?;
+
+        let (expected_tags, expected_categories) = get_tags_and_categories(&index)?;
+        assert_eq!(tags.get(), Some(expected_tags));
+        assert_eq!(categories.get(), Some(expected_categories));
+        assert!(!categories.get().unwrap().is_empty());
+
+        let outdated = TagList::new(Vec::new()).with_expires_at(Some(Utc::now()));
+        assert_eq!(outdated.get(), None);
+        let tags =
+            TagList::new(Vec::new()).with_expires_at(Some(Utc::now() + chrono::Duration::days(1)));
+        assert_eq!(tags.get(), Some(Vec::new()));
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+use tantivy::{
+    query::{AllQuery, BooleanQuery, Query},
+    Index,
+};
+
+use super::is_preview;
+use super::posts::filter_queries;
+use crate::text_engine::{
+    query::{get_archive, Visibility},
+    schema::{FieldGetter, JSONDcument, Projection},
+};
+
+#[derive(Debug, Deserialize)]
+pub struct ArchiveParams {
+    lang: Option<String>,
+    category: Option<String>,
+    posts: Option<bool>,
+    fields: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArchiveMonthResponse {
+    year: i32,
+    month: u32,
+    count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    posts: Option<Vec<JSONDcument>>,
+}
+
+#[get("/archive")]
+async fn get_archive_list(index: web::Data<Index>, req: HttpRequest) -> HttpResponse {
+    let schema = index.schema();
+    let fb = FieldGetter::new(&schema);
+    let params = match web::Query::<ArchiveParams>::from_query(req.query_string()) {
+        Ok(p) => p,
+        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+    };
+    let with_posts = params.posts.unwrap_or(false);
+    // summaries are enough for the archive
+    let projection = match params.fields {
+        Some(_) => Projection::from_query(&params.fields),
+        None => Ok(Projection::summary()),
+    };
+    let projection = match projection {
+        Ok(p) => p,
+        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+    };
+
+    let queries = filter_queries(&fb, &params.lang, &params.category, &None);
+    let q: Box<dyn Query> = if queries.is_empty() {
+        Box::new(AllQuery {})
+    } else {
+        Box::new(BooleanQuery::new(queries))
+    };
+    let q = Visibility::listing(is_preview(&req)).filter(q, &fb);
+
+    let archive = get_archive(&*q, &index, with_posts).and_then(|archive| {
+        archive
+            .into_iter()
+            .map(|month| {
+                let posts = if with_posts {
+                    Some(
+                        month
+                            .docs
+                            .iter()
+                            .map(|doc| fb.to_json(doc, &projection))
+                            .collect::<anyhow::Result<_>>()?,
+                    )
+                } else {
+                    None
+                };
+                Ok(ArchiveMonthResponse {
+                    year: month.year,
+                    month: month.month,
+                    count: month.count,
+                    posts,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()
+    });
+
+    match archive {
+        Ok(archive) => HttpResponse::Ok().json(archive),
+        Err(e) => {
+            error!("{:?}", e);
+            HttpResponse::InternalServerError().body("Internal Server Error")
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utility::*;
+    use actix_web::{dev::Service, http::StatusCode, test, App};
+    use tempdir::TempDir;
+    use urlencoding::encode;
+
+    #[actix_web::test]
+    async fn test_archive() {
+        let temp_dir = TempDir::new("test_archive").unwrap();
+        let (posts, index) = build_random_posts_index(5, temp_dir.path()).unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(index.clone()))
+                .service(get_archive_list),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/archive").to_request();
+        let resp = app.call(req).await.unwrap();
+        assert_eq!(resp.response().status(), StatusCode::OK);
+        let archive: serde_json::Value = test::read_body_json(resp).await;
+        let months = archive.as_array().unwrap();
+        let total: u64 = months.iter().map(|m| m["count"].as_u64().unwrap()).sum();
+        assert_eq!(total, posts.len() as u64);
+        assert!(months[0].get("posts").is_none());
+
+        let uri = format!(
+            "/archive?posts=true&category={}",
+            encode(&posts[0].category())
+        );
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let resp = app.call(req).await.unwrap();
+        let archive: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(archive[0]["count"], 1);
+        assert_eq!(archive[0]["posts"][0]["uuid"], posts[0].uuid());
+        assert!(archive[0]["posts"][0].get("body").is_none());
+    }
+}
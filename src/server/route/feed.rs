@@ -0,0 +1,147 @@
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use tantivy::{
+    query::{AllQuery, BooleanQuery, Query},
+    Index,
+};
+
+use super::is_preview;
+use super::posts::filter_queries;
+use crate::config;
+use crate::datetime::{Order, OrderBy};
+use crate::site::feed::{atom, rss, FeedContent, FeedEntry};
+use crate::text_engine::{
+    query::{get_page, Visibility},
+    schema::FieldGetter,
+};
+
+/// Number of entries in the feeds if `limit` is not specified
+const DEFAULT_FEED_LIMIT: usize = 20;
+
+#[derive(Debug, Deserialize)]
+pub struct FeedParams {
+    lang: Option<String>,
+    category: Option<String>,
+    tag: Option<String>,
+    limit: Option<usize>,
+    content: Option<FeedContent>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum FeedKind {
+    Rss,
+    Atom,
+}
+
+/// The latest posts by `created_at` in RSS or Atom
+fn feed_response(index: &Index, req: &HttpRequest, kind: FeedKind) -> HttpResponse {
+    let schema = index.schema();
+    let fb = FieldGetter::new(&schema);
+    let params = match web::Query::<FeedParams>::from_query(req.query_string()) {
+        Ok(p) => p,
+        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+    };
+    let content = params.content.unwrap_or(FeedContent::Description);
+    let site = &config::get().site;
+
+    let queries = filter_queries(&fb, &params.lang, &params.category, &params.tag);
+    let q: Box<dyn Query> = if queries.is_empty() {
+        Box::new(AllQuery {})
+    } else {
+        Box::new(BooleanQuery::new(queries))
+    };
+    let q = Visibility::listing(is_preview(req)).filter(q, &fb);
+
+    let entries = get_page(
+        &*q,
+        index,
+        Some(OrderBy::CreatedAt),
+        Order::Desc,
+        0,
+        params.limit.unwrap_or(DEFAULT_FEED_LIMIT),
+    )
+    .and_then(|(_, docs)| {
+        docs.iter()
+            .map(|doc| FeedEntry::from_doc(doc, &schema, site, content))
+            .collect::<anyhow::Result<Vec<_>>>()
+    });
+    let entries = match entries {
+        Ok(entries) => entries,
+        Err(e) => {
+            error!("{:?}", e);
+            return HttpResponse::InternalServerError().body("Internal Server Error");
+        }
+    };
+
+    let feed_url = format!("{}{}", site.base_url, req.uri());
+    match kind {
+        FeedKind::Rss => HttpResponse::Ok()
+            .content_type("application/rss+xml; charset=utf-8")
+            .body(rss(site, &feed_url, &entries)),
+        FeedKind::Atom => HttpResponse::Ok()
+            .content_type("application/atom+xml; charset=utf-8")
+            .body(atom(site, &feed_url, &entries, content)),
+    }
+}
+
+#[get("/feed.xml")]
+async fn get_rss(index: web::Data<Index>, req: HttpRequest) -> HttpResponse {
+    feed_response(&index, &req, FeedKind::Rss)
+}
+
+#[get("/atom.xml")]
+async fn get_atom(index: web::Data<Index>, req: HttpRequest) -> HttpResponse {
+    feed_response(&index, &req, FeedKind::Atom)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utility::*;
+    use actix_web::{dev::Service, http::StatusCode, test, App};
+    use tempdir::TempDir;
+    use urlencoding::encode;
+
+    #[actix_web::test]
+    async fn test_feeds() {
+        let temp_dir = TempDir::new("test_feeds").unwrap();
+        let (posts, index) = build_random_posts_index(5, temp_dir.path()).unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(index.clone()))
+                .service(get_rss)
+                .service(get_atom),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/feed.xml?limit=3")
+            .to_request();
+        let resp = app.call(req).await.unwrap();
+        assert_eq!(resp.response().status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get("content-type").unwrap(),
+            "application/rss+xml; charset=utf-8"
+        );
+        let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        assert_eq!(body.matches("<item>").count(), 3);
+
+        let uri = format!(
+            "/atom.xml?content=html&category={}",
+            encode(&posts[0].category())
+        );
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let resp = app.call(req).await.unwrap();
+        assert_eq!(resp.response().status(), StatusCode::OK);
+        let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        assert_eq!(body.matches("<entry>").count(), 1);
+        assert!(body.contains(&format!("<id>urn:uuid:{}</id>", posts[0].uuid())));
+        assert!(body.contains("<content type=\"html\">"));
+
+        let req = test::TestRequest::get()
+            .uri("/atom.xml?content=pdf")
+            .to_request();
+        let resp = app.call(req).await.unwrap();
+        assert_eq!(resp.response().status(), StatusCode::BAD_REQUEST);
+    }
+}
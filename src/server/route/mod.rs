@@ -1,33 +1,129 @@
+pub mod archive;
+pub mod feed;
 pub mod openapi;
 pub mod posts;
 pub mod search;
+pub mod sitemap;
 
-use super::{CategoryList, TagList};
+use super::{CategoryList, Preview, TagList};
 
-use actix_web::{get, web, HttpResponse, Responder};
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use serde::Deserialize;
+use tantivy::Index;
+
+use crate::{
+    posts::Lang,
+    text_engine::{
+        query::{category_tree, count_terms, Visibility},
+        schema::PostField,
+    },
+};
 
 #[get("/")]
 async fn hello() -> impl Responder {
     "Hello, Smark!"
 }
 
+/// Whether drafts and scheduled posts are served for the request
+pub fn is_preview(req: &HttpRequest) -> bool {
+    req.app_data::<web::Data<Preview>>()
+        .map(|preview| preview.allows(req))
+        .unwrap_or(false)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TermListParams {
+    lang: Option<String>,
+    counts: Option<bool>,
+}
+
+/// Names of tags or categories, or `{name, count, langs}` objects with `counts=true`.
+/// The snapshot of the published posts is returned if neither `lang` nor `counts` is specified
+/// and the request is not a preview, unless a scheduled post is published after the snapshot.
+fn term_list_response(
+    snapshot: impl FnOnce() -> Option<Vec<String>>,
+    index: &Index,
+    pf: PostField,
+    req: &HttpRequest,
+) -> HttpResponse {
+    let params = match web::Query::<TermListParams>::from_query(req.query_string()) {
+        Ok(p) => p,
+        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+    };
+    let with_counts = params.counts.unwrap_or(false);
+    let preview = is_preview(req);
+    if params.lang.is_none() && !with_counts && !preview {
+        if let Some(names) = snapshot() {
+            return HttpResponse::Ok().json(names);
+        }
+    }
+
+    let lang = match params.lang.as_deref().map(Lang::from_str).transpose() {
+        Ok(lang) => lang,
+        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+    };
+    match count_terms(index, pf, lang.as_ref(), Visibility::listing(preview)) {
+        Ok(counts) if with_counts => HttpResponse::Ok().json(counts),
+        Ok(counts) => {
+            HttpResponse::Ok().json(counts.into_iter().map(|c| c.name).collect::<Vec<_>>())
+        }
+        Err(e) => {
+            error!("{:?}", e);
+            HttpResponse::InternalServerError().body("Internal Server Error")
+        }
+    }
+}
+
 #[get("/tags")]
-async fn tag_list(tags: web::Data<TagList>) -> impl Responder {
-    let tags = tags.into_inner().0.clone();
-    info!("tags: {:?}", tags);
-    HttpResponse::Ok().json(tags)
+async fn tag_list(
+    tags: web::Data<TagList>,
+    index: web::Data<Index>,
+    req: HttpRequest,
+) -> impl Responder {
+    term_list_response(|| tags.get(), &index, PostField::Tags, &req)
 }
 
 #[get("/categories")]
-async fn category_list(categories: web::Data<CategoryList>) -> impl Responder {
-    let categories = categories.into_inner().0.clone();
-    info!("categories: {:?}", categories);
-    HttpResponse::Ok().json(categories)
+async fn category_list(
+    categories: web::Data<CategoryList>,
+    index: web::Data<Index>,
+    req: HttpRequest,
+) -> impl Responder {
+    term_list_response(|| categories.get(), &index, PostField::Category, &req)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CategoryTreeParams {
+    lang: Option<String>,
+}
+
+#[get("/categories/tree")]
+async fn category_tree_list(index: web::Data<Index>, req: HttpRequest) -> impl Responder {
+    let params = match web::Query::<CategoryTreeParams>::from_query(req.query_string()) {
+        Ok(p) => p,
+        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+    };
+    let lang = match params.lang.as_deref().map(Lang::from_str).transpose() {
+        Ok(lang) => lang,
+        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+    };
+
+    match category_tree(&index, lang.as_ref(), Visibility::listing(is_preview(&req))) {
+        Ok(tree) => HttpResponse::Ok().json(tree),
+        Err(e) => {
+            error!("{:?}", e);
+            HttpResponse::InternalServerError().body("Internal Server Error")
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::{
+        test_utility::*,
+        text_engine::query::{CategoryNode, TermCount},
+    };
     use actix_web::{
         dev::Service,
         http::StatusCode,
@@ -35,6 +131,8 @@ mod test {
         web::{self, Bytes},
         App,
     };
+    use chrono::Utc;
+    use tempdir::TempDir;
 
     #[actix_web::test]
     async fn test_health_check() {
@@ -47,12 +145,15 @@ mod test {
 
     #[actix_web::test]
     async fn test_tags_categories() {
+        let temp_dir = TempDir::new("test_tags_categories").unwrap();
+        let (_, index) = build_random_posts_index(1, temp_dir.path()).unwrap();
         let tags: Vec<String> = vec!["A", "B", "C"].iter().map(|x| x.to_string()).collect();
         let categories: Vec<String> = vec!["A", "B", "C"].iter().map(|x| x.to_string()).collect();
         let app = test::init_service(
             App::new()
-                .app_data(web::Data::new(CategoryList(categories.clone())))
-                .app_data(web::Data::new(TagList(tags.clone())))
+                .app_data(web::Data::new(index))
+                .app_data(web::Data::new(CategoryList::new(categories.clone())))
+                .app_data(web::Data::new(TagList::new(tags.clone())))
                 .service(tag_list)
                 .service(category_list),
         )
@@ -67,4 +168,99 @@ mod test {
             assert_eq!(v, Bytes::from_static(b"[\"A\",\"B\",\"C\"]"));
         }
     }
+
+    #[actix_web::test]
+    async fn test_tags_categories_outdated() {
+        let temp_dir = TempDir::new("test_tags_categories_outdated").unwrap();
+        let (posts, index) = build_random_posts_index(1, temp_dir.path()).unwrap();
+        let outdated = || vec!["outdated".to_string()];
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(index))
+                .app_data(web::Data::new(
+                    CategoryList::new(outdated()).with_expires_at(Some(Utc::now())),
+                ))
+                .app_data(web::Data::new(
+                    TagList::new(outdated()).with_expires_at(Some(Utc::now())),
+                ))
+                .service(tag_list)
+                .service(category_list),
+        )
+        .await;
+
+        // the snapshot expired at publish_at is not used
+        let req = test::TestRequest::get().uri("/categories").to_request();
+        let names: Vec<String> = test::read_body_json(app.call(req).await.unwrap()).await;
+        assert_eq!(names, vec![posts[0].category()]);
+        let req = test::TestRequest::get().uri("/tags").to_request();
+        let names: Vec<String> = test::read_body_json(app.call(req).await.unwrap()).await;
+        assert!(!names.contains(&"outdated".to_string()));
+    }
+
+    #[actix_web::test]
+    async fn test_tags_categories_counts() {
+        let temp_dir = TempDir::new("test_tags_categories_counts").unwrap();
+        let (posts, index) = build_random_posts_index(10, temp_dir.path()).unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(index))
+                .app_data(web::Data::new(CategoryList::new(Vec::new())))
+                .app_data(web::Data::new(TagList::new(Vec::new())))
+                .service(tag_list)
+                .service(category_list),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/categories?counts=true")
+            .to_request();
+        let resp = app.call(req).await.unwrap();
+        assert_eq!(resp.response().status(), StatusCode::OK);
+        let counts: Vec<TermCount> = test::read_body_json(resp).await;
+        assert_eq!(counts.iter().map(|c| c.count).sum::<usize>(), posts.len());
+
+        let lang = posts[0].lang();
+        let req = test::TestRequest::get()
+            .uri(&format!("/categories?lang={}", lang.as_str()))
+            .to_request();
+        let resp = app.call(req).await.unwrap();
+        let names: Vec<String> = test::read_body_json(resp).await;
+        assert!(names.contains(&posts[0].category()));
+        assert!(names.iter().all(|name| posts
+            .iter()
+            .any(|p| p.lang() == lang && &p.category() == name)));
+
+        let req = test::TestRequest::get().uri("/tags?lang=fr").to_request();
+        let resp = app.call(req).await.unwrap();
+        assert_eq!(resp.response().status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_category_tree() {
+        let temp_dir = TempDir::new("test_category_tree").unwrap();
+        let (posts, index) = build_random_posts_index(5, temp_dir.path()).unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(index))
+                .service(category_tree_list),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/categories/tree")
+            .to_request();
+        let resp = app.call(req).await.unwrap();
+        assert_eq!(resp.response().status(), StatusCode::OK);
+        let tree: Vec<CategoryNode> = test::read_body_json(resp).await;
+        assert_eq!(
+            tree.iter().map(|n| n.count).sum::<u64>(),
+            posts.len() as u64
+        );
+
+        let req = test::TestRequest::get()
+            .uri("/categories/tree?lang=fr")
+            .to_request();
+        let resp = app.call(req).await.unwrap();
+        assert_eq!(resp.response().status(), StatusCode::BAD_REQUEST);
+    }
 }
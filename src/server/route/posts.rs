@@ -1,58 +1,219 @@
 use actix_web::{get, web, HttpRequest, HttpResponse};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::ops::Deref;
+use std::ops::{Bound, Deref};
 use tantivy::{
     collector::Count,
     query::{AllQuery, BooleanQuery, Occur, Query, TermQuery},
     schema::IndexRecordOption,
-    Index, Term,
+    Document, Index, Term,
 };
 
 use crate::{
     posts::Lang,
     text_engine::{
-        query::{get_all, get_by_slug_with_lang, get_by_uuid},
-        schema::{FieldGetter, PostField},
+        query::{
+            date_range_query, get_adjacent, get_by_slug_with_fallback, get_by_slug_with_lang,
+            get_by_uuid, get_page, get_translations, related_query, Visibility,
+        },
+        schema::{
+            category_facet, BodyFormat, FieldGetter, JSONDcument, PostField, Projection,
+            Translation,
+        },
     },
 };
 
-use crate::datetime;
+use super::is_preview;
+use crate::datetime::{self, Order};
 
-#[get("/post/uuid/{uuid}")]
-async fn get_post_by_id(index: web::Data<Index>, uuid: web::Path<String>) -> HttpResponse {
+#[derive(Debug, Deserialize)]
+pub struct FieldsParams {
+    fields: Option<String>,
+    format: Option<BodyFormat>,
+}
+
+/// Json of the single post with the translations in `visibility` if they are projected
+fn single_post_json(
+    doc: &Document,
+    index: &Index,
+    projection: &Projection,
+    visibility: Visibility,
+) -> anyhow::Result<JSONDcument> {
     let schema = index.schema();
-    let fg = FieldGetter::new(&schema);
-    match get_by_uuid(&uuid.to_owned(), index.into_inner().deref()) {
-        Ok(doc) => HttpResponse::Ok().json(fg.to_json(&doc).unwrap()),
+    let fb = FieldGetter::new(&schema);
+    let mut jd = fb.to_json(doc, projection)?;
+    if projection.translations() {
+        let translations = get_translations(doc, index, visibility)?
+            .iter()
+            .map(|doc| Translation::from_doc(doc, &fb))
+            .collect::<anyhow::Result<_>>()?;
+        jd.set_translations(translations);
+    }
+    Ok(jd)
+}
+
+/// The post of `uuid`, which is regarded as not found if it is hidden by `visibility`
+fn get_visible_by_uuid(
+    uuid: &str,
+    index: &Index,
+    visibility: Visibility,
+) -> anyhow::Result<Document> {
+    let schema = index.schema();
+    let fb = FieldGetter::new(&schema);
+    let doc = get_by_uuid(uuid, index)?;
+    match visibility.allows(&doc, &fb)? {
+        true => Ok(doc),
+        false => Err(anyhow::anyhow!("{} is Not Found", uuid)),
+    }
+}
+
+#[get("/post/uuid/{uuid}")]
+async fn get_post_by_id(
+    index: web::Data<Index>,
+    uuid: web::Path<String>,
+    req: HttpRequest,
+) -> HttpResponse {
+    let index = index.into_inner();
+    let projection = match web::Query::<FieldsParams>::from_query(req.query_string())
+        .map_err(anyhow::Error::from)
+        .and_then(|p| Projection::single(&p.fields, p.format))
+    {
+        Ok(p) => p,
+        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+    };
+    let visibility = Visibility::single(is_preview(&req));
+
+    match get_visible_by_uuid(&uuid, index.deref(), visibility) {
+        Ok(doc) => match single_post_json(&doc, &index, &projection, visibility) {
+            Ok(jd) => HttpResponse::Ok().json(jd),
+            Err(e) => {
+                error!("{:?}", e);
+                HttpResponse::InternalServerError().body("Internal Server Error")
+            }
+        },
         Err(e) => HttpResponse::NotFound().body(e.to_string()),
     }
 }
 
+/// Number of related posts returned if `limit` is not specified
+const DEFAULT_RELATED_LIMIT: usize = 5;
+
+#[derive(Debug, Deserialize)]
+pub struct RelatedParams {
+    limit: Option<usize>,
+    fields: Option<String>,
+}
+
+#[get("/post/uuid/{uuid}/related")]
+async fn get_related_posts(
+    index: web::Data<Index>,
+    uuid: web::Path<String>,
+    req: HttpRequest,
+) -> HttpResponse {
+    let index = index.into_inner();
+    let schema = index.schema();
+    let fb = FieldGetter::new(&schema);
+    let params = match web::Query::<RelatedParams>::from_query(req.query_string()) {
+        Ok(p) => p,
+        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+    };
+    let projection = match Projection::from_query(&params.fields) {
+        Ok(p) => p,
+        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+    };
+    let limit = params.limit.unwrap_or(DEFAULT_RELATED_LIMIT);
+
+    let preview = is_preview(&req);
+    let doc = match get_visible_by_uuid(&uuid, &index, Visibility::single(preview)) {
+        Ok(doc) => doc,
+        Err(e) => return HttpResponse::NotFound().body(e.to_string()),
+    };
+
+    let (total, docs) = match related_query(&doc, &index)
+        .map(|q| Visibility::listing(preview).filter(q, &fb))
+        .and_then(|q| get_page(&*q, &index, None, Order::Desc, 0, limit))
+    {
+        Ok(page) => page,
+        Err(e) => {
+            error!("{:?}", e);
+            return HttpResponse::InternalServerError().body("Internal Server Error");
+        }
+    };
+
+    match docs
+        .iter()
+        .map(|doc| fb.to_json(doc, &projection))
+        .collect::<anyhow::Result<Vec<_>>>()
+    {
+        Ok(docs) => HttpResponse::Ok().json(Page::new(total, 0, limit, docs)),
+        Err(e) => {
+            error!("{:?}", e);
+            HttpResponse::InternalServerError().body("Internal Server Error")
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct GetSlugParams {
     slug: String,
     lang: Option<String>,
+    /// return the post in the default language if `lang` is missing. true by default
+    fallback: Option<bool>,
+    fields: Option<String>,
+    format: Option<BodyFormat>,
+}
+
+/// The post of `slug` in `lang`, which is regarded as not found if it is hidden by `visibility`
+fn get_visible_by_slug(
+    slug: &str,
+    lang: &str,
+    index: &Index,
+    visibility: Visibility,
+) -> anyhow::Result<Document> {
+    let schema = index.schema();
+    let fb = FieldGetter::new(&schema);
+    let doc = get_by_slug_with_lang(slug, lang, index)?;
+    match visibility.allows(&doc, &fb)? {
+        true => Ok(doc),
+        false => Err(anyhow::anyhow!(
+            "slug: {} and lang: {} is Not Found",
+            slug,
+            lang
+        )),
+    }
 }
 
 #[get("/post/slug")]
 async fn get_post_by_slug_and_lang(index: web::Data<Index>, req: HttpRequest) -> HttpResponse {
     let index = index.into_inner();
-    let schema = index.schema();
-    let fb = FieldGetter::new(&schema);
     let params = match web::Query::<GetSlugParams>::from_query(req.query_string()) {
         Ok(p) => p,
         Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
     };
+    let projection = match Projection::single(&params.fields, params.format) {
+        Ok(p) => p,
+        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+    };
 
-    let lang = if let Some(lang) = params.lang.as_ref() {
-        lang.to_owned()
-    } else {
-        Lang::Ja.to_string()
+    let lang = match params.lang.as_deref().map(Lang::from_str).transpose() {
+        Ok(lang) => lang.unwrap_or_else(Lang::default_lang).to_string(),
+        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
     };
 
-    let doc = match get_by_slug_with_lang(&params.slug, &lang, &index) {
-        Ok(_doc) => match fb.to_json(&_doc) {
-            Ok(doc) => doc,
+    let visibility = Visibility::single(is_preview(&req));
+    let found = if params.fallback.unwrap_or(true) {
+        get_by_slug_with_fallback(&params.slug, &lang, &index, visibility)
+    } else {
+        get_visible_by_slug(&params.slug, &lang, &index, visibility).map(|doc| (doc, false))
+    };
+    let doc = match found {
+        Ok((doc, fallback)) => match single_post_json(&doc, &index, &projection, visibility) {
+            Ok(mut doc) => {
+                if fallback {
+                    doc.set_lang_fallback();
+                }
+                doc
+            }
             Err(e) => {
                 error!("{:?}", e);
                 return HttpResponse::InternalServerError().body("Internal Server Error");
@@ -70,46 +231,339 @@ async fn get_post_by_slug_and_lang(index: web::Data<Index>, req: HttpRequest) ->
     HttpResponse::Ok().json(doc)
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Posts sharing `lang`, `category` or any of `tag` with the post.
+/// `tag` is ignored for the post without tags.
+#[derive(Debug, Default, PartialEq)]
+pub struct AdjacentScope {
+    lang: bool,
+    category: bool,
+    tag: bool,
+}
+
+impl AdjacentScope {
+    /// Parse comma separated scopes such as `lang,category`
+    pub fn parse(scope: &Option<String>) -> anyhow::Result<Self> {
+        let mut parsed = Self::default();
+        for s in scope.iter().flat_map(|s| s.split(',')).map(|s| s.trim()) {
+            match s {
+                "lang" => parsed.lang = true,
+                "category" => parsed.category = true,
+                "tag" => parsed.tag = true,
+                "" => continue,
+                _ => return Err(anyhow::anyhow!("{} is not a valid scope", s)),
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn to_queries(
+        &self,
+        fb: &FieldGetter,
+        doc: &Document,
+    ) -> anyhow::Result<Vec<(Occur, Box<dyn Query>)>> {
+        let term_query = |pf: PostField, text: &str| -> Box<dyn Query> {
+            Box::new(TermQuery::new(
+                Term::from_field_text(fb.get_field(pf), text),
+                IndexRecordOption::Basic,
+            ))
+        };
+
+        let mut queries = Vec::new();
+        if self.lang {
+            let lang = fb.get_text(doc, PostField::Lang)?;
+            queries.push((Occur::Must, term_query(PostField::Lang, &lang)));
+        }
+        if self.category {
+            let category = fb.get_text(doc, PostField::Category)?;
+            queries.push((Occur::Must, term_query(PostField::Category, &category)));
+        }
+        let tags = fb.get_tags(doc)?;
+        // an empty boolean query matches nothing
+        if self.tag && !tags.is_empty() {
+            let tags = tags
+                .iter()
+                .map(|tag| (Occur::Should, term_query(PostField::Tags, tag)))
+                .collect();
+            let q: Box<dyn Query> = Box::new(BooleanQuery::new(tags));
+            queries.push((Occur::Must, q));
+        }
+        Ok(queries)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Adjacent<T: Serialize> {
+    prev: Option<T>,
+    next: Option<T>,
+}
+
+fn adjacent_response(
+    doc: &Document,
+    index: &Index,
+    scope: &Option<String>,
+    fields: &Option<String>,
+    preview: bool,
+) -> HttpResponse {
+    let schema = index.schema();
+    let fb = FieldGetter::new(&schema);
+    let (scope, projection) = match AdjacentScope::parse(scope)
+        .and_then(|scope| Ok((scope, Projection::from_query(fields)?)))
+    {
+        Ok(p) => p,
+        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+    };
+
+    let adjacent = scope
+        .to_queries(&fb, doc)
+        .and_then(|mut filters| {
+            filters.extend(Visibility::listing(preview).queries(&fb));
+            get_adjacent(doc, &filters, index)
+        })
+        .and_then(|(prev, next)| {
+            Ok(Adjacent {
+                prev: prev.map(|d| fb.to_json(&d, &projection)).transpose()?,
+                next: next.map(|d| fb.to_json(&d, &projection)).transpose()?,
+            })
+        });
+    match adjacent {
+        Ok(adjacent) => HttpResponse::Ok().json(adjacent),
+        Err(e) => {
+            error!("{:?}", e);
+            HttpResponse::InternalServerError().body("Internal Server Error")
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AdjacentParams {
+    scope: Option<String>,
+    fields: Option<String>,
+}
+
+#[get("/post/uuid/{uuid}/adjacent")]
+async fn get_adjacent_posts_by_id(
+    index: web::Data<Index>,
+    uuid: web::Path<String>,
+    req: HttpRequest,
+) -> HttpResponse {
+    let params = match web::Query::<AdjacentParams>::from_query(req.query_string()) {
+        Ok(p) => p,
+        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+    };
+
+    let preview = is_preview(&req);
+    match get_visible_by_uuid(&uuid, &index, Visibility::single(preview)) {
+        Ok(doc) => adjacent_response(&doc, &index, &params.scope, &params.fields, preview),
+        Err(e) => HttpResponse::NotFound().body(e.to_string()),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AdjacentSlugParams {
+    slug: String,
+    lang: Option<String>,
+    scope: Option<String>,
+    fields: Option<String>,
+}
+
+#[get("/post/slug/adjacent")]
+async fn get_adjacent_posts_by_slug(index: web::Data<Index>, req: HttpRequest) -> HttpResponse {
+    let params = match web::Query::<AdjacentSlugParams>::from_query(req.query_string()) {
+        Ok(p) => p,
+        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+    };
+    let lang = match params.lang.as_deref().map(Lang::from_str).transpose() {
+        Ok(lang) => lang.unwrap_or_else(Lang::default_lang).to_string(),
+        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+    };
+
+    let preview = is_preview(&req);
+    match get_visible_by_slug(&params.slug, &lang, &index, Visibility::single(preview)) {
+        Ok(doc) => adjacent_response(&doc, &index, &params.scope, &params.fields, preview),
+        Err(e) => HttpResponse::NotFound().body(e.to_string()),
+    }
+}
+
+/// Whether posts should have any or all of the tags in `tag`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
-pub enum Order {
-    Asc,
-    Desc,
+pub enum TagMode {
+    Any,
+    All,
 }
 
+/// Keys which can be repeated in the query string such as `tag=a&tag=b`
+const MULTI_VALUE_KEYS: [&str; 3] = ["tag", "exclude_tag", "exclude_category"];
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GetPostsQueryParams {
     lang: Option<String>,
     category: Option<String>,
-    tag: Option<String>,
+    #[serde(skip)]
+    tags: Vec<String>,
+    tag_mode: Option<TagMode>,
+    #[serde(skip)]
+    exclude_tags: Vec<String>,
+    #[serde(skip)]
+    exclude_categories: Vec<String>,
+    created_after: Option<String>,
+    created_before: Option<String>,
+    updated_after: Option<String>,
+    updated_before: Option<String>,
     order_by: Option<datetime::OrderBy>,
     order: Option<Order>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    fields: Option<String>,
 }
 
-impl GetPostsQueryParams {
-    pub fn to_queries(&self, fb: &FieldGetter) -> Vec<(Occur, Box<dyn Query>)> {
-        let field_iter = [PostField::Lang, PostField::Category, PostField::Tags].iter();
-
-        [
-            self.lang.to_owned(),
-            self.category.to_owned(),
-            self.tag.to_owned(),
-        ]
+/// Term of `category`. `category` starting with `/` is the facet, which matches its descendants.
+fn category_term(fb: &FieldGetter, category: &str) -> Term {
+    if category.starts_with('/') {
+        Term::from_facet(
+            fb.get_field(PostField::CategoryFacet),
+            &category_facet(category),
+        )
+    } else {
+        Term::from_field_text(fb.get_field(PostField::Category), category)
+    }
+}
+
+/// Term queries to filter posts by `lang`, `category` and `tag`.
+/// `category` starting with `/` such as `/programming` also matches its descendants.
+pub fn filter_queries(
+    fb: &FieldGetter,
+    lang: &Option<String>,
+    category: &Option<String>,
+    tag: &Option<String>,
+) -> Vec<(Occur, Box<dyn Query>)> {
+    let field_iter = [PostField::Lang, PostField::Category, PostField::Tags].iter();
+
+    [lang.to_owned(), category.to_owned(), tag.to_owned()]
         .into_iter()
         .zip(field_iter)
         .flat_map(|(val, &pf)| {
             if let Some(val) = val {
-                let field = fb.get_field(pf);
-                let q: Box<dyn Query> = Box::new(TermQuery::new(
-                    Term::from_field_text(field, &val),
-                    IndexRecordOption::Basic,
-                ));
+                let term = if pf == PostField::Category {
+                    category_term(fb, &val)
+                } else {
+                    Term::from_field_text(fb.get_field(pf), &val)
+                };
+                let q: Box<dyn Query> = Box::new(TermQuery::new(term, IndexRecordOption::Basic));
                 Some((Occur::Must, q))
             } else {
                 None
             }
         })
         .collect()
+}
+
+/// Range queries of `created_at` and `updated_at`, whose bounds are parsed by `datetime::parse_datetime`.
+/// `*_after` includes the bound, and `*_before` excludes it.
+pub fn date_range_queries(
+    fb: &FieldGetter,
+    created_after: &Option<String>,
+    created_before: &Option<String>,
+    updated_after: &Option<String>,
+    updated_before: &Option<String>,
+) -> anyhow::Result<Vec<(Occur, Box<dyn Query>)>> {
+    let parse = |date: &Option<String>| -> anyhow::Result<Option<DateTime<Utc>>> {
+        date.as_deref()
+            .map(|date| Ok(datetime::parse_datetime(date, None)?.1))
+            .transpose()
+    };
+
+    let mut queries = Vec::new();
+    for (pf, after, before) in [
+        (PostField::CreatedAt, created_after, created_before),
+        (PostField::UpdatedAt, updated_after, updated_before),
+    ] {
+        let (after, before) = (parse(after)?, parse(before)?);
+        if after.is_none() && before.is_none() {
+            continue;
+        }
+        queries.push((
+            Occur::Must,
+            date_range_query(
+                fb.get_field(pf),
+                after.map_or(Bound::Unbounded, Bound::Included),
+                before.map_or(Bound::Unbounded, Bound::Excluded),
+            ),
+        ));
+    }
+    Ok(queries)
+}
+
+impl GetPostsQueryParams {
+    /// Parse the query string, where `tag`, `exclude_tag` and `exclude_category` can be repeated.
+    pub fn from_query(query: &str) -> anyhow::Result<Self> {
+        let pairs: Vec<(String, String)> = serde_urlencoded::from_str(query)?;
+        let (multi_values, rest): (Vec<_>, Vec<_>) = pairs
+            .into_iter()
+            .partition(|(key, _)| MULTI_VALUE_KEYS.contains(&key.as_str()));
+
+        let mut params: Self = serde_urlencoded::from_str(&serde_urlencoded::to_string(rest)?)?;
+        for (key, value) in multi_values.into_iter() {
+            match key.as_str() {
+                "tag" => params.tags.push(value),
+                "exclude_tag" => params.exclude_tags.push(value),
+                _ => params.exclude_categories.push(value),
+            }
+        }
+        Ok(params)
+    }
+
+    pub fn tag_mode(&self) -> TagMode {
+        self.tag_mode.unwrap_or(TagMode::All)
+    }
+
+    pub fn to_queries(&self, fb: &FieldGetter) -> anyhow::Result<Vec<(Occur, Box<dyn Query>)>> {
+        let term_query = |term: Term| -> Box<dyn Query> {
+            Box::new(TermQuery::new(term, IndexRecordOption::Basic))
+        };
+        let tag_term = |tag: &String| Term::from_field_text(fb.get_field(PostField::Tags), tag);
+
+        let mut queries = filter_queries(fb, &self.lang, &self.category, &None);
+        match self.tag_mode() {
+            TagMode::All => queries.extend(
+                self.tags
+                    .iter()
+                    .map(|tag| (Occur::Must, term_query(tag_term(tag)))),
+            ),
+            TagMode::Any if !self.tags.is_empty() => {
+                let tags = self
+                    .tags
+                    .iter()
+                    .map(|tag| (Occur::Should, term_query(tag_term(tag))))
+                    .collect();
+                let q: Box<dyn Query> = Box::new(BooleanQuery::new(tags));
+                queries.push((Occur::Must, q));
+            }
+            TagMode::Any => (),
+        }
+        queries.extend(
+            self.exclude_tags
+                .iter()
+                .map(|tag| (Occur::MustNot, term_query(tag_term(tag)))),
+        );
+        queries.extend(
+            self.exclude_categories
+                .iter()
+                .map(|category| (Occur::MustNot, term_query(category_term(fb, category)))),
+        );
+        queries.extend(date_range_queries(
+            fb,
+            &self.created_after,
+            &self.created_before,
+            &self.updated_after,
+            &self.updated_before,
+        )?);
+
+        // boolean queries only with exclusions match nothing
+        if !queries.is_empty() && queries.iter().all(|(occur, _)| *occur == Occur::MustNot) {
+            queries.push((Occur::Must, Box::new(AllQuery {})));
+        }
+        Ok(queries)
     }
 
     pub fn order_by(&self) -> Option<datetime::OrderBy> {
@@ -119,6 +573,51 @@ impl GetPostsQueryParams {
     pub fn get_order(&self) -> Order {
         self.order.to_owned().unwrap_or(Order::Desc)
     }
+
+    pub fn offset(&self) -> usize {
+        self.offset.unwrap_or(0)
+    }
+
+    /// all posts after `offset` if limit is not specified
+    pub fn limit(&self) -> usize {
+        self.limit.unwrap_or(usize::MAX)
+    }
+
+    pub fn projection(&self) -> anyhow::Result<Projection> {
+        Projection::from_query(&self.fields)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Page<T: Serialize> {
+    total: usize,
+    offset: usize,
+    limit: usize,
+    next: Option<usize>,
+    prev: Option<usize>,
+    posts: Vec<T>,
+}
+
+impl<T: Serialize> Page<T> {
+    /// `next` and `prev` are the offsets of the next and previous pages.
+    pub fn new(total: usize, offset: usize, limit: usize, posts: Vec<T>) -> Self {
+        let end = offset.saturating_add(posts.len());
+        let next = if end < total { Some(end) } else { None };
+        let prev = if offset > 0 {
+            Some(offset.saturating_sub(limit).min(total))
+        } else {
+            None
+        };
+
+        Self {
+            total,
+            offset,
+            limit: limit.min(total),
+            next,
+            prev,
+            posts,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -131,17 +630,21 @@ async fn count_posts(req: HttpRequest, index: web::Data<Index>) -> HttpResponse
     let index = index.into_inner();
     let schema = index.schema();
     let fb = FieldGetter::new(&schema);
-    let params = match web::Query::<GetPostsQueryParams>::from_query(req.query_string()) {
+    let params = match GetPostsQueryParams::from_query(req.query_string()) {
         Ok(p) => p,
         Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
     };
 
-    let queries = params.to_queries(&fb);
+    let queries = match params.to_queries(&fb) {
+        Ok(queries) => queries,
+        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+    };
     let query: Box<dyn Query> = if queries.is_empty() {
         Box::new(AllQuery {})
     } else {
         Box::new(BooleanQuery::new(queries))
     };
+    let query = Visibility::listing(is_preview(&req)).filter(query, &fb);
 
     let counter = Count {};
     let searcher = index.reader().expect("Not error here").searcher();
@@ -159,48 +662,67 @@ async fn get_posts(req: HttpRequest, index: web::Data<Index>) -> HttpResponse {
     let index = index.into_inner();
     let schema = index.schema();
     let fb = FieldGetter::new(&schema);
-    let params = match web::Query::<GetPostsQueryParams>::from_query(req.query_string()) {
+    let params = match GetPostsQueryParams::from_query(req.query_string()) {
         Ok(p) => p,
         Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
     };
 
-    let queries = params.to_queries(&fb);
+    let projection = match params.projection() {
+        Ok(p) => p,
+        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+    };
 
-    let __docs = if queries.is_empty() {
-        let q: Box<dyn Query> = Box::new(AllQuery {});
-        get_all(&q, index.deref(), params.order_by())
+    let queries = match params.to_queries(&fb) {
+        Ok(queries) => queries,
+        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+    };
+    let q: Box<dyn Query> = if queries.is_empty() {
+        Box::new(AllQuery {})
     } else {
-        let q: Box<dyn Query> = Box::new(BooleanQuery::new(queries));
-        get_all(&q, index.deref(), params.order_by())
+        Box::new(BooleanQuery::new(queries))
     };
+    let q = Visibility::listing(is_preview(&req)).filter(q, &fb);
 
-    let _docs = match __docs {
-        Ok(_docs) => _docs,
+    let (total, docs) = match get_page(
+        &q,
+        index.deref(),
+        params.order_by(),
+        params.get_order(),
+        params.offset(),
+        params.limit(),
+    ) {
+        Ok(page) => page,
         Err(e) => {
             error!("{:?}", e);
-            let empty: Vec<String> = Vec::new();
-            return HttpResponse::Ok().json(empty);
+            return HttpResponse::InternalServerError().body("Internal Server Error");
         }
     };
 
-    let mut docs = if let Some(docs) = _docs {
-        docs.iter().flat_map(|doc| fb.to_json(doc).ok()).collect()
-    } else {
-        Vec::new()
-    };
-    info!("{:?}", docs);
-    match params.get_order() {
-        Order::Asc => docs.reverse(),
-        Order::Desc => {}
+    match docs
+        .iter()
+        .map(|doc| fb.to_json(doc, &projection))
+        .collect::<anyhow::Result<Vec<_>>>()
+    {
+        Ok(docs) => {
+            HttpResponse::Ok().json(Page::new(total, params.offset(), params.limit(), docs))
+        }
+        Err(e) => {
+            error!("{:?}", e);
+            HttpResponse::InternalServerError().body("Internal Server Error")
+        }
     }
-
-    HttpResponse::Ok().json(docs)
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::{test_utility::*, text_engine::query::put};
+    use crate::{
+        datetime::{DateTimeFormat, DateTimeWithFormat},
+        posts::{frontmatter::Publication, Post},
+        server::Preview,
+        test_utility::*,
+        text_engine::{query::put, schema::build_schema},
+    };
     use actix_web::{dev::Service, http::StatusCode, test, web, App};
     use anyhow::Result;
     use tempdir::TempDir;
@@ -218,6 +740,14 @@ mod test {
         query_params: &str,
         index: &Index,
     ) -> Result<(Counter, Vec<PostResponse>)> {
+        let (counter, page) = test_count_and_get_page(query_params, index).await?;
+        Ok((counter, page.posts))
+    }
+
+    async fn test_count_and_get_page(
+        query_params: &str,
+        index: &Index,
+    ) -> Result<(Counter, PageResponse)> {
         let app = test::init_service(
             App::new()
                 .app_data(web::Data::new(index.clone()))
@@ -274,11 +804,10 @@ mod test {
         .unwrap();
         let post_num = 5;
         let (posts, index) = build_random_posts_index(post_num, temp_dir.path()).unwrap();
-        let lang_posts_num = posts.iter().filter(|x| x.lang() == Lang::En).count();
-        let (count, posts) =
-            test_count_and_get_posts(&format!("?lang={}", Lang::En.as_str()), &index)
-                .await
-                .unwrap();
+        let lang_posts_num = posts.iter().filter(|x| x.lang().as_str() == "en").count();
+        let (count, posts) = test_count_and_get_posts(&format!("?lang={}", "en"), &index)
+            .await
+            .unwrap();
 
         assert_eq!(posts.len(), lang_posts_num);
         assert_eq!(count.count, lang_posts_num);
@@ -309,6 +838,35 @@ mod test {
         Ok(())
     }
 
+    #[actix_web::test]
+    async fn test_posts_count_get_category_descendants() -> Result<()> {
+        let temp_dir = uuid_tempdir();
+        let (_, index) = build_random_posts_index(3, temp_dir.path())?;
+        let mut index_writer = index.writer(100_000_000)?;
+        for category in [
+            "programming/rust/async",
+            "programming/rust",
+            "programming",
+            "life",
+        ] {
+            put_post_with(&index, &mut index_writer, category, rand_lang(), None, None)?;
+        }
+
+        for (category, expected) in [
+            ("/programming", 3),
+            ("/programming/rust", 2),
+            ("programming", 1),
+            ("/programming/python", 0),
+        ] {
+            let (count, posts) =
+                test_count_and_get_posts(&format!("?category={}", encode(category)), &index)
+                    .await?;
+            assert_eq!(count.count, expected);
+            assert_eq!(posts.len(), expected);
+        }
+        Ok(())
+    }
+
     #[actix_web::test]
     async fn test_posts_count_get_tags() {
         let temp_dir = uuid_tempdir();
@@ -343,6 +901,90 @@ mod test {
         assert_eq!(count.count, 1);
     }
 
+    #[actix_web::test]
+    async fn test_posts_count_get_multiple_tags() {
+        let temp_dir = uuid_tempdir();
+
+        let (mut posts, index) = build_random_posts_index(5, temp_dir.path()).unwrap();
+        *posts[0].tags_mut() = Some(vec!["test0".to_string(), "test1".to_string()]);
+        *posts[1].tags_mut() = Some(vec!["test1".to_string(), "test2".to_string()]);
+        *posts[2].tags_mut() = Some(vec!["test2".to_string()]);
+        let mut index_writer = index.writer(100000000).unwrap();
+        for post in posts.iter().take(3) {
+            put(post, &index, &mut index_writer, false).unwrap();
+        }
+        let category = posts[0].category();
+        let others = posts.iter().filter(|p| p.category() != category).count();
+
+        for (query_params, expected) in [
+            ("?tag=test1&tag=test2", 1),
+            ("?tag=test1&tag=test2&tag_mode=all", 1),
+            ("?tag=test0&tag=test2&tag_mode=any", 3),
+            ("?tag=test1&exclude_tag=test0", 1),
+            ("?exclude_tag=test1&exclude_tag=test2", 2),
+            (&format!("?exclude_category={}", encode(&category)), others),
+        ] {
+            let (count, posts) = test_count_and_get_posts(query_params, &index)
+                .await
+                .unwrap();
+            assert_eq!(count.count, expected, "{}", query_params);
+            assert_eq!(posts.len(), expected, "{}", query_params);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_posts_count_get_date_ranges() {
+        let temp_dir = uuid_tempdir();
+        let (_, index) = build_random_posts_index(0, temp_dir.path()).unwrap();
+        let mut index_writer = index.writer(100000000).unwrap();
+        for (created_at, updated_at) in [
+            ("2020-12-31", "2021-01-10"),
+            ("2021-01-01", "2021-01-01"),
+            ("2021-06-01T12:00:00+09:00", "2022-03-01"),
+            ("2022-01-01", "2022-01-01"),
+        ] {
+            put_post_with(
+                &index,
+                &mut index_writer,
+                "category",
+                rand_lang(),
+                Some(created_at),
+                Some(updated_at),
+            )
+            .unwrap();
+        }
+
+        for (query_params, expected) in [
+            ("?created_after=2021-01-01&created_before=2022-01-01", 2),
+            ("?created_after=2021-01-01", 3),
+            ("?created_before=2021-06-01T03:00:00Z", 2),
+            ("?updated_after=2022/01/01", 2),
+            ("?created_after=2021-01-01&updated_before=2022-01-01", 1),
+        ] {
+            let (count, posts) = test_count_and_get_posts(query_params, &index)
+                .await
+                .unwrap();
+            assert_eq!(count.count, expected, "{}", query_params);
+            assert_eq!(posts.len(), expected, "{}", query_params);
+        }
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(index.clone()))
+                .service(get_posts)
+                .service(count_posts),
+        )
+        .await;
+        for uri in [
+            "/posts?created_after=someday",
+            "/post/count?updated_before=1",
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = app.call(req).await.unwrap();
+            assert_eq!(resp.response().status(), StatusCode::BAD_REQUEST);
+        }
+    }
+
     #[actix_web::test]
     async fn test_posts_count_get_not_found() {
         let temp_dir = TempDir::new(&format!(
@@ -362,6 +1004,41 @@ mod test {
         assert_eq!(count.count, 0);
     }
 
+    #[actix_web::test]
+    async fn test_posts_pagination() {
+        let temp_dir = uuid_tempdir();
+        let (_, index) = build_random_posts_index(5, temp_dir.path()).unwrap();
+
+        let (_, all) = test_count_and_get_page("?order_by=created_at", &index)
+            .await
+            .unwrap();
+        assert_eq!(all.total, 5);
+        assert_eq!(all.posts.len(), 5);
+        assert_eq!(all.next, None);
+        assert_eq!(all.prev, None);
+
+        let (count, page) =
+            test_count_and_get_page("?order_by=created_at&offset=2&limit=2", &index)
+                .await
+                .unwrap();
+        assert_eq!(count.count, 5);
+        assert_eq!(page.total, 5);
+        assert_eq!(page.next, Some(4));
+        assert_eq!(page.prev, Some(0));
+        let uuids: Vec<&String> = page.posts.iter().map(|p| &p.uuid).collect();
+        let expected: Vec<&String> = all.posts[2..4].iter().map(|p| &p.uuid).collect();
+        assert_eq!(uuids, expected);
+
+        let (_, last) =
+            test_count_and_get_page("?order_by=created_at&order=asc&offset=4&limit=2", &index)
+                .await
+                .unwrap();
+        assert_eq!(last.posts.len(), 1);
+        assert_eq!(last.posts[0].uuid, all.posts[0].uuid);
+        assert_eq!(last.next, None);
+        assert_eq!(last.prev, Some(2));
+    }
+
     #[actix_web::test]
     async fn test_get_by_uuid() {
         let temp_dir = TempDir::new(&format!(
@@ -389,6 +1066,397 @@ mod test {
         }
     }
 
+    #[actix_web::test]
+    async fn test_translations() -> Result<()> {
+        let temp_dir = uuid_tempdir();
+        let (_, index) = build_random_posts_index(3, temp_dir.path())?;
+        let mut index_writer = index.writer(100_000_000)?;
+        let original = Post::new(
+            "hello".to_string(),
+            rand_matter_with_lang(Lang::from_str("ja")?),
+            "body".to_string(),
+        );
+        let translation = Post::new(
+            "hola".to_string(),
+            rand_matter_with_lang(Lang::from_str("en")?).with_translation_of(Some(original.uuid())),
+            "body".to_string(),
+        );
+        let only_ja = Post::new(
+            "solo".to_string(),
+            rand_matter_with_lang(Lang::from_str("ja")?),
+            "body".to_string(),
+        );
+        for post in [&original, &translation, &only_ja] {
+            put(post, &index, &mut index_writer, false)?;
+        }
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(index.clone()))
+                .service(get_post_by_id)
+                .service(get_post_by_slug_and_lang),
+        )
+        .await;
+
+        let get = |uri: String| test::TestRequest::get().uri(&uri).to_request();
+        let resp = app
+            .call(get(format!("/post/uuid/{}", original.uuid())))
+            .await
+            .unwrap();
+        let p: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(
+            p["translations"],
+            serde_json::json!([{"lang": "en", "uuid": translation.uuid(), "slug": "hola"}])
+        );
+        assert!(p.get("lang_fallback").is_none());
+
+        // the translation with another slug is in the requested language
+        let resp = app
+            .call(get("/post/slug?slug=hello&lang=en".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(resp.response().status(), StatusCode::OK);
+        let p: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(p["uuid"], translation.uuid());
+        assert!(p.get("lang_fallback").is_none());
+
+        // fall back to the default language
+        let resp = app
+            .call(get("/post/slug?slug=solo&lang=en".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(resp.response().status(), StatusCode::OK);
+        let p: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(p["uuid"], only_ja.uuid());
+        assert_eq!(p["lang_fallback"], true);
+
+        let resp = app
+            .call(get(
+                "/post/slug?slug=solo&lang=en&fallback=false".to_string()
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.response().status(), StatusCode::NOT_FOUND);
+
+        let resp = app
+            .call(get(
+                "/post/slug?slug=hola&lang=en&fields=uuid,translations".to_string()
+            ))
+            .await
+            .unwrap();
+        let p: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(p.as_object().unwrap().len(), 2);
+        assert_eq!(p["translations"][0]["uuid"], original.uuid());
+
+        Ok(())
+    }
+
+    #[actix_web::test]
+    async fn test_publication() -> Result<()> {
+        let temp_dir = uuid_tempdir();
+        let (_, index) = build_random_posts_index(2, temp_dir.path())?;
+        let mut index_writer = index.writer(100_000_000)?;
+        let post = |publication: Publication| {
+            let matter = rand_matter().with_publication(publication);
+            Post::new(rand_alpahbet(10), matter, "body".to_string())
+        };
+        let publish_at = |days: i64| {
+            Some(DateTimeWithFormat::new(
+                chrono::Utc::now() + chrono::Duration::days(days),
+                DateTimeFormat::RFC3339,
+            ))
+        };
+        let draft = post(Publication {
+            draft: true,
+            ..Publication::default()
+        });
+        let scheduled = post(Publication {
+            publish_at: publish_at(1),
+            ..Publication::default()
+        });
+        let published = post(Publication {
+            publish_at: publish_at(-1),
+            ..Publication::default()
+        });
+        let unlisted = post(Publication {
+            unlisted: true,
+            ..Publication::default()
+        });
+        for post in [&draft, &scheduled, &published, &unlisted] {
+            put(post, &index, &mut index_writer, false)?;
+        }
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(index.clone()))
+                .app_data(web::Data::new(Preview::new(
+                    false,
+                    Some("token".to_string()),
+                )))
+                .service(get_post_by_id)
+                .service(get_post_by_slug_and_lang)
+                .service(count_posts),
+        )
+        .await;
+        let status = |resp: actix_web::dev::ServiceResponse| resp.response().status();
+
+        let req = test::TestRequest::get().uri("/post/count").to_request();
+        let counter: Counter = test::read_body_json(app.call(req).await.unwrap()).await;
+        assert_eq!(counter.count, 3);
+
+        for (post, expected) in [
+            (&draft, StatusCode::NOT_FOUND),
+            (&scheduled, StatusCode::NOT_FOUND),
+            (&published, StatusCode::OK),
+            (&unlisted, StatusCode::OK),
+        ] {
+            let uri = format!("/post/uuid/{}", post.uuid());
+            let req = test::TestRequest::get().uri(&uri).to_request();
+            assert_eq!(status(app.call(req).await.unwrap()), expected);
+        }
+        let uri = format!(
+            "/post/slug?slug={}&lang={}&fallback=false",
+            unlisted.slug(),
+            unlisted.lang().as_str()
+        );
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        assert_eq!(status(app.call(req).await.unwrap()), StatusCode::OK);
+
+        // preview by the token
+        let req = test::TestRequest::get()
+            .uri("/post/count")
+            .insert_header(("X-Preview-Token", "token"))
+            .to_request();
+        let counter: Counter = test::read_body_json(app.call(req).await.unwrap()).await;
+        assert_eq!(counter.count, 5);
+        let uri = format!("/post/uuid/{}", draft.uuid());
+        let req = test::TestRequest::get()
+            .uri(&uri)
+            .insert_header(("X-Preview-Token", "token"))
+            .to_request();
+        assert_eq!(status(app.call(req).await.unwrap()), StatusCode::OK);
+        let req = test::TestRequest::get()
+            .uri(&uri)
+            .insert_header(("X-Preview-Token", "wrong"))
+            .to_request();
+        assert_eq!(status(app.call(req).await.unwrap()), StatusCode::NOT_FOUND);
+        let uri = format!("/post/uuid/{}?preview_token=token", draft.uuid());
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        assert_eq!(status(app.call(req).await.unwrap()), StatusCode::NOT_FOUND);
+
+        Ok(())
+    }
+
+    #[actix_web::test]
+    async fn test_fields_projection() {
+        let temp_dir = uuid_tempdir();
+        let (posts, index) = build_random_posts_index(3, temp_dir.path()).unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(index.clone()))
+                .service(get_post_by_id)
+                .service(get_post_by_slug_and_lang)
+                .service(get_posts),
+        )
+        .await;
+
+        let uri = format!("/post/uuid/{}?fields=uuid,title", posts[0].uuid());
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let resp = app.call(req).await.unwrap();
+        assert_eq!(resp.response().status(), StatusCode::OK);
+        let p: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(p.as_object().unwrap().len(), 2);
+        assert_eq!(p["uuid"], posts[0].uuid());
+
+        let uri = format!("/post/uuid/{}?fields=uuid,toc", posts[0].uuid());
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let resp = app.call(req).await.unwrap();
+        let p: serde_json::Value = test::read_body_json(resp).await;
+        assert!(p["toc"].is_array());
+
+        let uri = format!(
+            "/post/slug?slug={}&lang={}&fields=summary",
+            posts[0].slug(),
+            posts[0].lang().as_str()
+        );
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let resp = app.call(req).await.unwrap();
+        assert_eq!(resp.response().status(), StatusCode::OK);
+        let p: serde_json::Value = test::read_body_json(resp).await;
+        assert!(p.get("body").is_none());
+        assert!(p.get("excerpt").is_some());
+
+        let req = test::TestRequest::get()
+            .uri("/posts?fields=summary")
+            .to_request();
+        let resp = app.call(req).await.unwrap();
+        assert_eq!(resp.response().status(), StatusCode::OK);
+        let page: serde_json::Value = test::read_body_json(resp).await;
+        for post in page["posts"].as_array().unwrap() {
+            assert!(post.get("body").is_none());
+            assert!(post.get("title").is_some());
+        }
+
+        for fields in ["unknown", ""] {
+            let req = test::TestRequest::get()
+                .uri(&format!("/posts?fields={}", fields))
+                .to_request();
+            let resp = app.call(req).await.unwrap();
+            assert_eq!(resp.response().status(), StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_html_format() {
+        let temp_dir = uuid_tempdir();
+        let (mut posts, index) = build_random_posts_index(3, temp_dir.path()).unwrap();
+        *posts[0].body_mut() = "## Heading\n\n~~old~~ text".to_string();
+        let mut index_writer = index.writer(100_000_000).unwrap();
+        put(&posts[0], &index, &mut index_writer, false).unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(index.clone()))
+                .service(get_post_by_id)
+                .service(get_post_by_slug_and_lang),
+        )
+        .await;
+
+        for uri in [
+            format!("/post/uuid/{}?format=html", posts[0].uuid()),
+            format!(
+                "/post/slug?slug={}&lang={}&format=html",
+                posts[0].slug(),
+                posts[0].lang().as_str()
+            ),
+        ] {
+            let req = test::TestRequest::get().uri(&uri).to_request();
+            let resp = app.call(req).await.unwrap();
+            assert_eq!(resp.response().status(), StatusCode::OK);
+            let p: serde_json::Value = test::read_body_json(resp).await;
+            assert!(p.get("body").is_none());
+            assert_eq!(
+                p["html"],
+                "<h2 id=\"heading\">Heading</h2>\n<p><del>old</del> text</p>\n"
+            );
+            assert_eq!(p["toc"][0]["id"], "heading");
+            assert_eq!(p["toc"][0]["level"], 2);
+        }
+
+        let uri = format!("/post/uuid/{}?format=pdf", posts[0].uuid());
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let resp = app.call(req).await.unwrap();
+        assert_eq!(resp.response().status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_related_posts() {
+        let temp_dir = uuid_tempdir();
+        let (_, index) = build_random_posts_index(3, temp_dir.path()).unwrap();
+        let en = Lang::from_str("en").unwrap();
+        let posts: Vec<Post> = (0..4)
+            .map(|_| rand_post_with_lang(en.clone(), "related text"))
+            .collect();
+        let mut index_writer = index.writer(100_000_000).unwrap();
+        for post in posts.iter() {
+            put(post, &index, &mut index_writer, false).unwrap();
+        }
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(index.clone()))
+                .service(get_related_posts),
+        )
+        .await;
+
+        let uri = format!("/post/uuid/{}/related?limit=2", posts[0].uuid());
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let resp = app.call(req).await.unwrap();
+        assert_eq!(resp.response().status(), StatusCode::OK);
+        let page: PageResponse = test::read_body_json(resp).await;
+        assert_eq!(page.total, 3);
+        assert_eq!(page.limit, 2);
+        assert_eq!(page.next, Some(2));
+        assert_eq!(page.posts.len(), 2);
+        assert!(page.posts.iter().all(|p| p.uuid != posts[0].uuid()));
+
+        let req = test::TestRequest::get()
+            .uri("/post/uuid/a/related")
+            .to_request();
+        let resp = app.call(req).await.unwrap();
+        assert_eq!(resp.response().status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn test_adjacent_scope() {
+        assert_eq!(
+            AdjacentScope::parse(&Some("lang, category".to_string())).unwrap(),
+            AdjacentScope {
+                lang: true,
+                category: true,
+                tag: false,
+            }
+        );
+        assert_eq!(
+            AdjacentScope::parse(&None).unwrap(),
+            AdjacentScope::default()
+        );
+        assert!(AdjacentScope::parse(&Some("series".to_string())).is_err());
+
+        let schema = build_schema();
+        let fb = FieldGetter::new(&schema);
+        let scope = AdjacentScope::parse(&Some("tag".to_string())).unwrap();
+        let now = DateTimeWithFormat::default();
+        let mut post = rand_post();
+        *post.tags_mut() = None;
+        let doc = post.to_doc(&schema, &now, &now);
+        assert!(scope.to_queries(&fb, &doc).unwrap().is_empty());
+        *post.tags_mut() = Some(vec!["rust".to_string()]);
+        let doc = post.to_doc(&schema, &now, &now);
+        assert_eq!(scope.to_queries(&fb, &doc).unwrap().len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_adjacent_posts() {
+        let temp_dir = uuid_tempdir();
+        let (posts, index) = build_random_posts_index(3, temp_dir.path()).unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(index.clone()))
+                .service(get_adjacent_posts_by_id)
+                .service(get_adjacent_posts_by_slug),
+        )
+        .await;
+
+        for uri in [
+            format!("/post/uuid/{}/adjacent?scope=lang,tag", posts[0].uuid()),
+            format!(
+                "/post/slug/adjacent?slug={}&lang={}&fields=uuid",
+                posts[0].slug(),
+                posts[0].lang().as_str()
+            ),
+        ] {
+            let req = test::TestRequest::get().uri(&uri).to_request();
+            let resp = app.call(req).await.unwrap();
+            assert_eq!(resp.response().status(), StatusCode::OK);
+            let p: serde_json::Value = test::read_body_json(resp).await;
+            assert!(p.get("prev").is_some());
+            assert!(p.get("next").is_some());
+        }
+
+        let uri = format!("/post/uuid/{}/adjacent?scope=series", posts[0].uuid());
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let resp = app.call(req).await.unwrap();
+        assert_eq!(resp.response().status(), StatusCode::BAD_REQUEST);
+
+        let req = test::TestRequest::get()
+            .uri("/post/uuid/a/adjacent")
+            .to_request();
+        let resp = app.call(req).await.unwrap();
+        assert_eq!(resp.response().status(), StatusCode::NOT_FOUND);
+    }
+
     #[actix_web::test]
     async fn test_get_by_uuid_not_found() {
         let temp_dir = TempDir::new(&format!(
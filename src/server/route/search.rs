@@ -2,20 +2,82 @@ use actix_web::{get, web, HttpRequest, HttpResponse};
 use serde::Deserialize;
 use std::ops::Deref;
 use tantivy::{
-    collector::TopDocs,
-    query::{AllQuery, Query},
+    query::{AllQuery, BooleanQuery, BoostQuery, Occur, Query},
     Index,
 };
 
+use super::is_preview;
+use super::posts::{date_range_queries, filter_queries, Page};
+use crate::datetime::{self, Order};
+use crate::posts::Lang;
 use crate::text_engine::{
-    query::search,
-    schema::{FieldGetter, JSONDcument, PostField},
+    query::{get_scored_page, parse_query, Visibility},
+    schema::{FieldGetter, JSONDcument, Projection},
+    snippet::{PostSnippetGenerator, SnippetConfig},
 };
 
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchOrderBy {
+    Relevance,
+    CreatedAt,
+    UpdatedAt,
+}
+
+impl SearchOrderBy {
+    /// `None` means ordering by relevance
+    pub fn to_order_by(self) -> Option<datetime::OrderBy> {
+        match self {
+            SearchOrderBy::Relevance => None,
+            SearchOrderBy::CreatedAt => Some(datetime::OrderBy::CreatedAt),
+            SearchOrderBy::UpdatedAt => Some(datetime::OrderBy::UpdatedAt),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct SearchQueryParams {
     query: Option<String>,
+    lang: Option<String>,
+    category: Option<String>,
+    tag: Option<String>,
+    created_after: Option<String>,
+    created_before: Option<String>,
+    updated_after: Option<String>,
+    updated_before: Option<String>,
+    order_by: Option<SearchOrderBy>,
+    order: Option<Order>,
+    offset: Option<usize>,
     limit: Option<usize>,
+    fields: Option<String>,
+    snippet_length: Option<usize>,
+    highlight_pre: Option<String>,
+    highlight_post: Option<String>,
+}
+
+impl SearchQueryParams {
+    pub fn order_by(&self) -> Option<datetime::OrderBy> {
+        self.order_by
+            .unwrap_or(SearchOrderBy::Relevance)
+            .to_order_by()
+    }
+
+    pub fn get_order(&self) -> Order {
+        self.order.unwrap_or(Order::Desc)
+    }
+
+    pub fn snippet_config(&self) -> anyhow::Result<SnippetConfig> {
+        let default = SnippetConfig::default();
+        SnippetConfig::with_markers(
+            self.snippet_length.unwrap_or(default.max_num_chars),
+            self.highlight_pre
+                .as_deref()
+                .unwrap_or(&default.highlight_pre),
+            self.highlight_post
+                .as_deref()
+                .unwrap_or(&default.highlight_post),
+        )
+    }
 }
 
 #[get("/search")]
@@ -27,42 +89,109 @@ async fn search_posts(index: web::Data<Index>, req: HttpRequest) -> HttpResponse
         Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
     };
 
+    let projection = match Projection::from_query(&params.fields) {
+        Ok(p) => p,
+        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+    };
+
+    let snippet_config = match params.snippet_config() {
+        Ok(c) => c,
+        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+    };
+
     let fb = FieldGetter::new(&schema);
-    let fields = [PostField::Title, PostField::Description, PostField::RawText]
-        .into_iter()
-        .map(|pf| fb.get_field(pf))
-        .collect();
+    // search the fields of the requested language, or of all languages
+    let lang = match params.lang.as_deref().map(Lang::from_str).transpose() {
+        Ok(lang) => lang,
+        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+    };
+    let fields = fb.get_lang_fields(lang.as_ref());
 
+    let offset = params.offset.unwrap_or(0);
     let limit = if let Some(limit) = params.limit.as_ref() {
         *limit
     } else {
         10
     };
 
-    let docs: Vec<JSONDcument> = if let Some(query) = params.query.to_owned() {
-        match search(&query.to_lowercase(), fields, limit, index.deref()) {
-            Ok(docs) => docs,
+    let mut queries = Vec::new();
+    if let Some(query) = params.query.as_ref() {
+        match parse_query(&query.to_lowercase(), fields, index.deref()) {
+            Ok(q) => queries.push((Occur::Must, q)),
+            Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+        }
+    }
+    let mut filters = filter_queries(&fb, &params.lang, &params.category, &params.tag);
+    match date_range_queries(
+        &fb,
+        &params.created_after,
+        &params.created_before,
+        &params.updated_after,
+        &params.updated_before,
+    ) {
+        Ok(date_ranges) => filters.extend(date_ranges),
+        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+    }
+    // filters do not affect the relevance scores
+    queries.extend(filters.into_iter().map(|(occur, q)| {
+        let q: Box<dyn Query> = Box::new(BoostQuery::new(q, 0.0));
+        (occur, q)
+    }));
+    let q: Box<dyn Query> = if queries.is_empty() {
+        Box::new(AllQuery {})
+    } else {
+        Box::new(BooleanQuery::new(queries))
+    };
+    let q = Visibility::listing(is_preview(&req)).filter(q, &fb);
+
+    let (total, docs) = match get_scored_page(
+        &*q,
+        index.deref(),
+        params.order_by(),
+        params.get_order(),
+        offset,
+        limit,
+    ) {
+        Ok(page) => page,
+        Err(e) => {
+            error!("{:?}", e);
+            return HttpResponse::InternalServerError().body("Internal Server Error");
+        }
+    };
+
+    // scores and snippets are only for the search with query
+    let snippet_generator = if params.query.is_some() {
+        match PostSnippetGenerator::create(index.deref(), &*q, snippet_config) {
+            Ok(g) => Some(g),
             Err(e) => {
                 error!("{:?}", e);
                 return HttpResponse::InternalServerError().body("Internal Server Error");
             }
         }
-        .iter()
-        .flat_map(|doc| fb.to_json(doc))
-        .collect()
     } else {
-        let q: Box<dyn Query> = Box::new(AllQuery {});
-        let searcher = index.reader().expect("Not error in search api").searcher();
-
-        searcher
-            .search(&q, &TopDocs::with_limit(limit))
-            .unwrap()
-            .into_iter()
-            .flat_map(|(_, doc_address)| searcher.doc(doc_address).ok())
-            .flat_map(|doc| fb.to_json(&doc).ok())
-            .collect()
+        None
     };
-    HttpResponse::Ok().json(docs)
+
+    let docs = docs
+        .iter()
+        .map(|(score, doc)| {
+            let mut jd = fb.to_json(doc, &projection)?;
+            if params.query.is_some() {
+                jd.set_score(*score);
+            }
+            if let Some(g) = snippet_generator.as_ref() {
+                jd.set_snippets(g.snippets(doc)?);
+            }
+            anyhow::Ok(jd)
+        })
+        .collect::<anyhow::Result<Vec<JSONDcument>>>();
+    match docs {
+        Ok(docs) => HttpResponse::Ok().json(Page::new(total, offset, limit, docs)),
+        Err(e) => {
+            error!("{:?}", e);
+            HttpResponse::InternalServerError().body("Internal Server Error")
+        }
+    }
 }
 
 #[cfg(test)]
@@ -77,6 +206,10 @@ mod test_search {
     use urlencoding::encode;
 
     async fn test_search(index: Index, query_params: Option<&str>) -> Result<Vec<PostResponse>> {
+        Ok(test_search_page(index, query_params).await?.posts)
+    }
+
+    async fn test_search_page(index: Index, query_params: Option<&str>) -> Result<PageResponse> {
         let uri = if let Some(query_params) = query_params {
             format!("/search?{}", query_params)
         } else {
@@ -116,19 +249,118 @@ mod test_search {
             uuid::Uuid::new_v4().to_string()
         ))
         .unwrap();
-        let (mut posts, index) = build_random_posts_index(5, temp_dir.path()).unwrap();
-
-        let target_body = posts[0].body_mut();
-        *target_body = "検索でこのポストにヒットする".to_string();
+        let (_, index) = build_random_posts_index(5, temp_dir.path()).unwrap();
 
+        let post = rand_post_with_lang(
+            Lang::from_str("ja").unwrap(),
+            "検索でこのポストにヒットする",
+        );
         let mut index_writer = index.writer(100000000).unwrap();
-        let _ = put(&posts[0], &index, &mut index_writer, false).unwrap();
+        let _ = put(&post, &index, &mut index_writer, false).unwrap();
 
         let resp_posts = test_search(index, Some(&format!("query={}", encode("検索"))))
             .await
             .unwrap();
         assert_eq!(resp_posts.len(), 1);
-        assert_eq!(resp_posts[0].uuid, posts[0].uuid());
+        assert_eq!(resp_posts[0].uuid, post.uuid());
+    }
+
+    #[actix_web::test]
+    async fn test_posts_search_snippets() {
+        let temp_dir = TempDir::new(&format!("temp_rand_index_{}", uuid::Uuid::new_v4())).unwrap();
+        let (_, index) = build_random_posts_index(5, temp_dir.path()).unwrap();
+
+        let post = rand_post_with_lang(
+            Lang::from_str("en").unwrap(),
+            "this post has a keyword in body",
+        );
+        let mut index_writer = index.writer(100000000).unwrap();
+        put(&post, &index, &mut index_writer, false).unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(index.clone()))
+                .service(search_posts),
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/search?query=keywords&lang=en&highlight_pre={}&highlight_post={}",
+                encode("<em>"),
+                encode("</em>")
+            ))
+            .to_request();
+        let resp = app.call(req).await.unwrap();
+        assert_eq!(resp.response().status(), StatusCode::OK);
+
+        let page: serde_json::Value = test::read_body_json(resp).await;
+        let snippets = &page["posts"][0]["snippets"];
+        assert_eq!(page["total"], 1);
+        assert_eq!(snippets["body"], "this post has a <em>keyword</em> in body");
+        assert!(page["posts"][0]["score"].as_f64().unwrap() > 0.0);
+        assert!(snippets["title"].is_null());
+
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/search?query=keywords&lang=en&highlight_pre={}",
+                encode("<img src=x onerror=alert(1)>")
+            ))
+            .to_request();
+        let resp = app.call(req).await.unwrap();
+        assert_eq!(resp.response().status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_posts_search_filter_and_order() {
+        let temp_dir = TempDir::new(&format!("temp_rand_index_{}", uuid::Uuid::new_v4())).unwrap();
+        let (posts, index) = build_random_posts_index(5, temp_dir.path()).unwrap();
+
+        let category = posts[0].category();
+        let page = test_search_page(
+            index.clone(),
+            Some(&format!("category={}", encode(&category))),
+        )
+        .await
+        .unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.posts[0].uuid, posts[0].uuid());
+
+        let page = test_search_page(index.clone(), Some("order_by=created_at&order=asc"))
+            .await
+            .unwrap();
+        assert_eq!(page.total, 5);
+        assert!(page
+            .posts
+            .windows(2)
+            .all(|w| w[0].created_at <= w[1].created_at));
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(index.clone()))
+                .service(search_posts),
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .uri("/search?order_by=unknown")
+            .to_request();
+        let resp = app.call(req).await.unwrap();
+        assert_eq!(resp.response().status(), StatusCode::BAD_REQUEST);
+
+        let req = test::TestRequest::get()
+            .uri("/search?created_after=yesterday")
+            .to_request();
+        let resp = app.call(req).await.unwrap();
+        assert_eq!(resp.response().status(), StatusCode::BAD_REQUEST);
+
+        for (query_params, expected) in [
+            ("created_after=2000-01-01&updated_after=2000-01-01", 5),
+            ("created_before=2000-01-01", 0),
+        ] {
+            let page = test_search_page(index.clone(), Some(query_params))
+                .await
+                .unwrap();
+            assert_eq!(page.total, expected);
+        }
     }
 
     #[actix_web::test]
@@ -156,8 +388,9 @@ mod test_search {
         let resp = app.call(req).await.unwrap();
 
         assert_eq!(resp.response().status(), StatusCode::OK);
-        let resp_posts: Vec<PostResponse> = test::read_body_json(resp).await;
-        assert!(resp_posts.is_empty());
+        let resp_page: PageResponse = test::read_body_json(resp).await;
+        assert!(resp_page.posts.is_empty());
+        assert_eq!(resp_page.total, 0);
     }
 
     #[actix_web::test]
@@ -180,7 +413,23 @@ mod test_search {
         let resp = app.call(req).await.unwrap();
 
         assert_eq!(resp.response().status(), StatusCode::OK);
-        let resp_posts: Vec<PostResponse> = test::read_body_json(resp).await;
-        assert_eq!(resp_posts.len(), 2);
+        let resp_page: PageResponse = test::read_body_json(resp).await;
+        assert_eq!(resp_page.posts.len(), 2);
+        assert_eq!(resp_page.total, 5);
+        assert_eq!(resp_page.next, Some(2));
+    }
+
+    #[actix_web::test]
+    async fn test_posts_seach_offset() {
+        let temp_dir = TempDir::new(&format!("temp_rand_index_{}", uuid::Uuid::new_v4())).unwrap();
+        let (_, index) = build_random_posts_index(5, temp_dir.path()).unwrap();
+
+        let page = test_search_page(index, Some("offset=4&limit=2"))
+            .await
+            .unwrap();
+        assert_eq!(page.posts.len(), 1);
+        assert_eq!(page.total, 5);
+        assert_eq!(page.next, None);
+        assert_eq!(page.prev, Some(2));
     }
 }
@@ -0,0 +1,56 @@
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use tantivy::Index;
+
+use super::is_preview;
+use crate::config;
+use crate::site::sitemap::{sitemap, sitemap_entries};
+use crate::text_engine::query::Visibility;
+
+#[get("/sitemap.xml")]
+async fn get_sitemap(index: web::Data<Index>, req: HttpRequest) -> HttpResponse {
+    let visibility = Visibility::listing(is_preview(&req));
+    match sitemap_entries(&index, &config::get().site, visibility) {
+        Ok(entries) => HttpResponse::Ok()
+            .content_type("application/xml; charset=utf-8")
+            .body(sitemap(&entries)),
+        Err(e) => {
+            error!("{:?}", e);
+            HttpResponse::InternalServerError().body("Internal Server Error")
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utility::*;
+    use actix_web::{dev::Service, http::StatusCode, test, App};
+    use tempdir::TempDir;
+
+    #[actix_web::test]
+    async fn test_sitemap() {
+        let temp_dir = TempDir::new("test_sitemap_route").unwrap();
+        let (posts, index) = build_random_posts_index(3, temp_dir.path()).unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(index.clone()))
+                .service(get_sitemap),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/sitemap.xml").to_request();
+        let resp = app.call(req).await.unwrap();
+        assert_eq!(resp.response().status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get("content-type").unwrap(),
+            "application/xml; charset=utf-8"
+        );
+        let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        assert_eq!(body.matches("<url>").count(), 3);
+        assert!(body.contains(&format!(
+            "<loc>http://localhost:8080/{}/posts/{}</loc>",
+            posts[0].lang().as_str(),
+            posts[0].slug()
+        )));
+    }
+}
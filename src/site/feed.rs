@@ -0,0 +1,258 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use tantivy::{schema::Schema, Document};
+
+use super::escape_xml;
+use crate::config::SiteConfig;
+use crate::text_engine::schema::{FieldGetter, PostField};
+
+/// Content of feed entries
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeedContent {
+    Description,
+    Html,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedEntry {
+    pub uuid: String,
+    pub title: String,
+    pub link: String,
+    pub content: String,
+    pub categories: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl FeedEntry {
+    pub fn from_doc(
+        doc: &Document,
+        schema: &Schema,
+        site: &SiteConfig,
+        content: FeedContent,
+    ) -> Result<Self> {
+        let fb = FieldGetter::new(schema);
+        let uuid = fb.get_text(doc, PostField::Uuid)?;
+        let category = fb.get_text(doc, PostField::Category)?;
+        let link = site.post_url(
+            &fb.get_text(doc, PostField::Lang)?,
+            &fb.get_text(doc, PostField::Slug)?,
+            &uuid,
+            &category,
+        );
+        let content = match content {
+            FeedContent::Description => fb.get_text(doc, PostField::Description)?,
+            FeedContent::Html => fb.get_text(doc, PostField::Html)?,
+        };
+
+        let mut categories = vec![category];
+        categories.extend(fb.get_tags(doc)?);
+        categories.retain(|c| !c.is_empty());
+
+        Ok(Self {
+            uuid,
+            title: fb.get_text(doc, PostField::Title)?,
+            link,
+            content,
+            categories,
+            created_at: fb.get_date(doc, PostField::CreatedAt)?,
+            updated_at: fb.get_date(doc, PostField::UpdatedAt)?,
+        })
+    }
+}
+
+/// The latest update of the entries, or now if there are no entries
+fn last_updated(entries: &[FeedEntry]) -> DateTime<Utc> {
+    entries
+        .iter()
+        .map(|entry| entry.updated_at)
+        .max()
+        .unwrap_or_else(Utc::now)
+}
+
+/// RSS 2.0 feed. `feed_url` is the url of the feed itself.
+pub fn rss(site: &SiteConfig, feed_url: &str, entries: &[FeedEntry]) -> String {
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n");
+    xml.push_str("<channel>\n");
+    xml.push_str(&format!("<title>{}</title>\n", escape_xml(&site.title)));
+    xml.push_str(&format!("<link>{}</link>\n", escape_xml(&site.base_url)));
+    xml.push_str(&format!(
+        "<description>{}</description>\n",
+        escape_xml(&site.description)
+    ));
+    xml.push_str(&format!(
+        "<atom:link href=\"{}\" rel=\"self\" type=\"application/rss+xml\"/>\n",
+        escape_xml(feed_url)
+    ));
+    xml.push_str(&format!(
+        "<lastBuildDate>{}</lastBuildDate>\n",
+        last_updated(entries).to_rfc2822()
+    ));
+
+    for entry in entries.iter() {
+        xml.push_str("<item>\n");
+        xml.push_str(&format!("<title>{}</title>\n", escape_xml(&entry.title)));
+        xml.push_str(&format!("<link>{}</link>\n", escape_xml(&entry.link)));
+        xml.push_str(&format!(
+            "<guid isPermaLink=\"false\">{}</guid>\n",
+            escape_xml(&entry.uuid)
+        ));
+        xml.push_str(&format!(
+            "<pubDate>{}</pubDate>\n",
+            entry.created_at.to_rfc2822()
+        ));
+        xml.push_str(&format!(
+            "<description>{}</description>\n",
+            escape_xml(&entry.content)
+        ));
+        for category in entry.categories.iter() {
+            xml.push_str(&format!("<category>{}</category>\n", escape_xml(category)));
+        }
+        if let Some(author) = site.author.as_ref() {
+            xml.push_str(&format!(
+                "<dc:creator>{}</dc:creator>\n",
+                escape_xml(author)
+            ));
+        }
+        xml.push_str("</item>\n");
+    }
+
+    xml.push_str("</channel>\n</rss>\n");
+    xml
+}
+
+/// Atom feed. `feed_url` is the url of the feed itself.
+/// The description is emitted as text `summary`, and html as `content`.
+pub fn atom(
+    site: &SiteConfig,
+    feed_url: &str,
+    entries: &[FeedEntry],
+    content: FeedContent,
+) -> String {
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    xml.push_str(&format!("<title>{}</title>\n", escape_xml(&site.title)));
+    if !site.description.is_empty() {
+        xml.push_str(&format!(
+            "<subtitle>{}</subtitle>\n",
+            escape_xml(&site.description)
+        ));
+    }
+    xml.push_str(&format!(
+        "<link href=\"{}\"/>\n",
+        escape_xml(&site.base_url)
+    ));
+    xml.push_str(&format!(
+        "<link href=\"{}\" rel=\"self\"/>\n",
+        escape_xml(feed_url)
+    ));
+    xml.push_str(&format!("<id>{}/</id>\n", escape_xml(&site.base_url)));
+    xml.push_str(&format!(
+        "<updated>{}</updated>\n",
+        last_updated(entries).to_rfc3339()
+    ));
+    if let Some(author) = site.author.as_ref() {
+        xml.push_str(&format!(
+            "<author><name>{}</name></author>\n",
+            escape_xml(author)
+        ));
+    }
+
+    // the description is plain text, which is escaped as it is
+    let (content_tag, content_type) = match content {
+        FeedContent::Description => ("summary", "text"),
+        FeedContent::Html => ("content", "html"),
+    };
+    for entry in entries.iter() {
+        xml.push_str("<entry>\n");
+        xml.push_str(&format!("<title>{}</title>\n", escape_xml(&entry.title)));
+        xml.push_str(&format!("<link href=\"{}\"/>\n", escape_xml(&entry.link)));
+        xml.push_str(&format!("<id>urn:uuid:{}</id>\n", escape_xml(&entry.uuid)));
+        xml.push_str(&format!(
+            "<published>{}</published>\n",
+            entry.created_at.to_rfc3339()
+        ));
+        xml.push_str(&format!(
+            "<updated>{}</updated>\n",
+            entry.updated_at.to_rfc3339()
+        ));
+        xml.push_str(&format!(
+            "<{tag} type=\"{kind}\">{}</{tag}>\n",
+            escape_xml(&entry.content),
+            tag = content_tag,
+            kind = content_type
+        ));
+        for category in entry.categories.iter() {
+            xml.push_str(&format!("<category term=\"{}\"/>\n", escape_xml(category)));
+        }
+        xml.push_str("</entry>\n");
+    }
+
+    xml.push_str("</feed>\n");
+    xml
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::datetime::DateTimeWithFormat;
+
+    fn entry() -> FeedEntry {
+        FeedEntry {
+            uuid: "c1a2b3".to_string(),
+            title: "Rust & Wasm".to_string(),
+            link: "https://example.com/en/posts/rust".to_string(),
+            content: "<p>body</p>".to_string(),
+            categories: vec!["programming".to_string(), "rust".to_string()],
+            created_at: DateTimeWithFormat::from_str("2021-01-01")
+                .unwrap()
+                .datetime(),
+            updated_at: DateTimeWithFormat::from_str("2021-02-01")
+                .unwrap()
+                .datetime(),
+        }
+    }
+
+    fn site() -> SiteConfig {
+        SiteConfig {
+            title: "Blog".to_string(),
+            author: Some("smark".to_string()),
+            ..SiteConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_rss() {
+        let xml = rss(&site(), "http://localhost:8080/feed.xml", &[entry()]);
+        assert!(xml.contains("<title>Rust &amp; Wasm</title>"));
+        assert!(xml.contains("<guid isPermaLink=\"false\">c1a2b3</guid>"));
+        assert!(xml.contains("<pubDate>Fri, 01 Jan 2021 00:00:00 +0000</pubDate>"));
+        assert!(xml.contains("<lastBuildDate>Mon, 01 Feb 2021 00:00:00 +0000</lastBuildDate>"));
+        assert!(xml.contains("<description>&lt;p&gt;body&lt;/p&gt;</description>"));
+        assert!(xml.contains("<category>rust</category>"));
+        assert!(xml.contains("<dc:creator>smark</dc:creator>"));
+    }
+
+    #[test]
+    fn test_atom() {
+        let xml = atom(
+            &site(),
+            "http://localhost:8080/atom.xml",
+            &[entry()],
+            FeedContent::Html,
+        );
+        assert!(xml.contains("<id>urn:uuid:c1a2b3</id>"));
+        assert!(xml.contains("<updated>2021-02-01T00:00:00+00:00</updated>"));
+        assert!(xml.contains("<published>2021-01-01T00:00:00+00:00</published>"));
+        assert!(xml.contains("<content type=\"html\">&lt;p&gt;body&lt;/p&gt;</content>"));
+        assert!(xml.contains("<author><name>smark</name></author>"));
+
+        let xml = atom(&site(), "", &[entry()], FeedContent::Description);
+        assert!(xml.contains("<summary type=\"text\">"));
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::btree_map::{BTreeMap, Entry};
+
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat, Utc};
+use tantivy::{query::AllQuery, Document, Index};
+
+use super::escape_xml;
+use crate::config::SiteConfig;
+use crate::text_engine::{
+    query::{get_all, get_translations, Visibility},
+    schema::{FieldGetter, PostField},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SitemapEntry {
+    pub loc: String,
+    pub lastmod: DateTime<Utc>,
+    /// pairs of lang and url of the translations including the post itself, ordered by lang
+    pub alternates: Vec<(String, String)>,
+}
+
+fn post_url(doc: &Document, fb: &FieldGetter, site: &SiteConfig) -> Result<String> {
+    Ok(site.post_url(
+        &fb.get_text(doc, PostField::Lang)?,
+        &fb.get_text(doc, PostField::Slug)?,
+        &fb.get_text(doc, PostField::Uuid)?,
+        &fb.get_text(doc, PostField::Category)?,
+    ))
+}
+
+/// Sitemap entries of the posts in `visibility` ordered by slug and lang.
+/// Alternates are the translations given by `get_translations`, same as `translations` of the post.
+pub fn sitemap_entries(
+    index: &Index,
+    site: &SiteConfig,
+    visibility: Visibility,
+) -> Result<Vec<SitemapEntry>> {
+    let schema = index.schema();
+    let fb = FieldGetter::new(&schema);
+    let q = visibility.filter(Box::new(AllQuery {}), &fb);
+    let docs = get_all(&q, index, None)?.unwrap_or_default();
+
+    let mut entries: BTreeMap<(String, String), SitemapEntry> = BTreeMap::new();
+    for doc in docs.iter() {
+        let slug = fb.get_text(doc, PostField::Slug)?;
+        let lang = fb.get_text(doc, PostField::Lang)?;
+        let loc = post_url(doc, &fb, site)?;
+
+        let translations = get_translations(doc, index, visibility)?;
+        let mut alternates = Vec::new();
+        if !translations.is_empty() {
+            alternates.push((lang.clone(), loc.clone()));
+            for translation in translations.iter() {
+                alternates.push((
+                    fb.get_text(translation, PostField::Lang)?,
+                    post_url(translation, &fb, site)?,
+                ));
+            }
+            alternates.sort();
+        }
+
+        let entry = SitemapEntry {
+            loc,
+            lastmod: fb.get_date(doc, PostField::UpdatedAt)?,
+            alternates,
+        };
+        match entries.entry((slug, lang)) {
+            Entry::Vacant(vacant) => {
+                vacant.insert(entry);
+            }
+            Entry::Occupied(occupied) => warn!(
+                "{} is skipped in the sitemap, since slug: {} and lang: {} is duplicated",
+                entry.loc,
+                occupied.key().0,
+                occupied.key().1
+            ),
+        }
+    }
+    Ok(entries.into_values().collect())
+}
+
+/// Sitemap xml with `xhtml:link` of hreflang alternates
+pub fn sitemap(entries: &[SitemapEntry]) -> String {
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\" xmlns:xhtml=\"http://www.w3.org/1999/xhtml\">\n");
+    for entry in entries.iter() {
+        xml.push_str("<url>\n");
+        xml.push_str(&format!("<loc>{}</loc>\n", escape_xml(&entry.loc)));
+        xml.push_str(&format!(
+            "<lastmod>{}</lastmod>\n",
+            entry.lastmod.to_rfc3339_opts(SecondsFormat::Secs, true)
+        ));
+        for (lang, url) in entry.alternates.iter() {
+            xml.push_str(&format!(
+                "<xhtml:link rel=\"alternate\" hreflang=\"{}\" href=\"{}\"/>\n",
+                escape_xml(lang),
+                escape_xml(url)
+            ));
+        }
+        xml.push_str("</url>\n");
+    }
+    xml.push_str("</urlset>\n");
+    xml
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::posts::{Lang, Post};
+    use crate::test_utility::*;
+    use crate::text_engine::query::put;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_sitemap() -> Result<()> {
+        let temp_dir = TempDir::new("test_sitemap")?;
+        let (_, index) = build_random_posts_index(3, temp_dir.path())?;
+        let mut index_writer = index.writer(100_000_000)?;
+        let translations: Vec<Post> = ["ja", "en"]
+            .iter()
+            .map(|lang| {
+                let matter = rand_matter_with_lang(Lang::from_str(lang).unwrap());
+                Post::new("shared".to_string(), matter, "body".to_string())
+            })
+            .collect();
+        for post in translations.iter() {
+            put(post, &index, &mut index_writer, false)?;
+        }
+        // translation with the other slug is linked by translation_of
+        let original = Post::new(
+            "original".to_string(),
+            rand_matter_with_lang(Lang::from_str("ja")?),
+            "body".to_string(),
+        );
+        let translated = Post::new(
+            "translated".to_string(),
+            rand_matter_with_lang(Lang::from_str("en")?).with_translation_of(Some(original.uuid())),
+            "body".to_string(),
+        );
+        put(&original, &index, &mut index_writer, false)?;
+        put(&translated, &index, &mut index_writer, false)?;
+        // the duplicated slug and lang is skipped
+        let duplicated = Post::new(
+            "shared".to_string(),
+            rand_matter_with_lang(Lang::from_str("en")?),
+            "body".to_string(),
+        );
+        put(&duplicated, &index, &mut index_writer, false)?;
+
+        let site = SiteConfig {
+            base_url: "https://example.com".to_string(),
+            ..SiteConfig::default()
+        };
+        let entries = sitemap_entries(&index, &site, Visibility::listing(false))?;
+        assert_eq!(entries.len(), 7);
+
+        let translated: Vec<&SitemapEntry> = entries
+            .iter()
+            .filter(|entry| !entry.alternates.is_empty())
+            .collect();
+        assert_eq!(translated.len(), 4);
+        let alternates = vec![
+            (
+                "en".to_string(),
+                "https://example.com/en/posts/translated".to_string(),
+            ),
+            (
+                "ja".to_string(),
+                "https://example.com/ja/posts/original".to_string(),
+            ),
+        ];
+        assert_eq!(translated[0].alternates, alternates);
+        assert_eq!(
+            translated[0].loc,
+            "https://example.com/ja/posts/original".to_string()
+        );
+        assert_eq!(translated[3].alternates, alternates);
+        assert_eq!(
+            translated[1].alternates,
+            vec![
+                (
+                    "en".to_string(),
+                    "https://example.com/en/posts/shared".to_string()
+                ),
+                (
+                    "ja".to_string(),
+                    "https://example.com/ja/posts/shared".to_string()
+                ),
+            ]
+        );
+
+        let xml = sitemap(&entries);
+        assert_eq!(xml.matches("<url>").count(), 7);
+        assert!(xml.contains(
+            "<xhtml:link rel=\"alternate\" hreflang=\"en\" href=\"https://example.com/en/posts/shared\"/>"
+        ));
+        assert!(xml.contains(&format!(
+            "<lastmod>{}</lastmod>",
+            translations[0]
+                .updated_at()
+                .unwrap()
+                .datetime()
+                .to_rfc3339_opts(SecondsFormat::Secs, true)
+        )));
+        Ok(())
+    }
+}
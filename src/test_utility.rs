@@ -9,8 +9,7 @@ use crate::{
 use anyhow::Result;
 use rand::prelude::IteratorRandom;
 use serde::{Deserialize, Serialize};
-use strum::IntoEnumIterator;
-use tantivy::Index;
+use tantivy::{Index, IndexWriter};
 
 pub const TITLE_LENGTH: usize = 10;
 pub const DESCRIPTION_LENGTH: usize = 100;
@@ -44,7 +43,7 @@ pub fn rand_japanase(size: usize) -> String {
 #[cfg(not(tarpaulin_include))]
 pub fn rand_lang() -> Lang {
     let mut rng = &mut rand::thread_rng();
-    Lang::iter().choose(&mut rng).unwrap()
+    Lang::all().into_iter().choose(&mut rng).unwrap()
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -64,24 +63,65 @@ pub fn rand_tags(size: usize) -> Option<Vec<String>> {
 
 #[cfg(not(tarpaulin_include))]
 pub fn rand_matter() -> FrontMatter {
+    rand_matter_with_lang(rand_lang())
+}
+
+#[cfg(not(tarpaulin_include))]
+pub fn rand_matter_with_lang(lang: Lang) -> FrontMatter {
     let tags = rand_tags(3);
     FrontMatter::new(
         uuid::Uuid::new_v4(),
         rand_japanase(TITLE_LENGTH),
         rand_japanase(DESCRIPTION_LENGTH),
         rand_japanase(TAG_CATEGORIES_LENGTH),
-        rand_lang(),
+        lang,
         tags,
         Some(DateTimeWithFormat::default()),
         Some(DateTimeWithFormat::default()),
     )
 }
 
+/// Post of `category` and `lang` put into `index`, with the other fields fixed.
+/// Dates are parsed from the strings and kept as they are, or filled with now if they are `None`.
+#[cfg(not(tarpaulin_include))]
+pub fn put_post_with(
+    index: &Index,
+    index_writer: &mut IndexWriter,
+    category: &str,
+    lang: Lang,
+    created_at: Option<&str>,
+    updated_at: Option<&str>,
+) -> Result<Post> {
+    let date = |date: Option<&str>| date.map(DateTimeWithFormat::from_str).transpose();
+    let matter = FrontMatter::new(
+        uuid::Uuid::new_v4(),
+        "title",
+        "description",
+        category,
+        lang,
+        None,
+        date(created_at)?,
+        date(updated_at)?,
+    );
+    let post = Post::new(rand_alpahbet(10), matter, "body".to_string());
+    put(&post, index, index_writer, true)?;
+    Ok(post)
+}
+
 #[cfg(not(tarpaulin_include))]
 pub fn rand_post() -> Post {
     Post::new(rand_alpahbet(10), rand_matter(), rand_japanase(BODY_LENGHT))
 }
 
+#[cfg(not(tarpaulin_include))]
+pub fn rand_post_with_lang(lang: Lang, body: &str) -> Post {
+    Post::new(
+        rand_alpahbet(10),
+        rand_matter_with_lang(lang),
+        body.to_string(),
+    )
+}
+
 #[cfg(not(tarpaulin_include))]
 pub fn build_random_posts_index(post_size: usize, index_path: &Path) -> Result<(Vec<Post>, Index)> {
     let posts: Vec<Post> = (0..post_size).map(|_| rand_post()).collect();
@@ -110,6 +150,17 @@ pub struct PostResponse {
     pub updated_at: String,
 }
 
+#[cfg(not(tarpaulin_include))]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PageResponse {
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+    pub next: Option<usize>,
+    pub prev: Option<usize>,
+    pub posts: Vec<PostResponse>,
+}
+
 impl PartialEq<Post> for PostResponse {
     fn eq(&self, other: &Post) -> bool {
         let mut flag = self.uuid == other.uuid()
@@ -7,14 +7,98 @@ use lindera_tantivy::tokenizer::LinderaTokenizer;
 use tantivy::schema::*;
 use tantivy::Index;
 
+use tantivy::tokenizer::Language;
 use tantivy::tokenizer::LowerCaser;
+use tantivy::tokenizer::NgramTokenizer;
 use tantivy::tokenizer::RawTokenizer;
+use tantivy::tokenizer::RemoveLongFilter;
+use tantivy::tokenizer::SimpleTokenizer;
+use tantivy::tokenizer::Stemmer;
+use tantivy::tokenizer::StopWordFilter;
 use tantivy::tokenizer::TextAnalyzer;
 use tantivy::tokenizer::WhitespaceTokenizer;
 use tantivy::Result;
+use tantivy::TantivyError;
 
+use super::schema::PostField;
+use crate::config::{self, TokenizerKind};
 use crate::posts::Lang;
 
+fn build_analyzer(kind: TokenizerKind) -> TextAnalyzer {
+    match kind {
+        TokenizerKind::Lindera => {
+            let config = TokenizerConfig {
+                dict_path: None,
+                user_dict_path: None,
+                user_dict_bin_path: None,
+                mode: Mode::Decompose(Penalty::default()),
+            };
+            TextAnalyzer::from(LinderaTokenizer::with_config(config).unwrap()).filter(LowerCaser)
+        }
+        TokenizerKind::Stemmer(Language::English) => TextAnalyzer::from(SimpleTokenizer)
+            .filter(RemoveLongFilter::limit(40))
+            .filter(LowerCaser)
+            .filter(StopWordFilter::default())
+            .filter(Stemmer::new(Language::English)),
+        TokenizerKind::Stemmer(language) => TextAnalyzer::from(SimpleTokenizer)
+            .filter(RemoveLongFilter::limit(40))
+            .filter(LowerCaser)
+            .filter(Stemmer::new(language)),
+        TokenizerKind::Ngram => {
+            TextAnalyzer::from(NgramTokenizer::all_ngrams(1, 2)).filter(LowerCaser)
+        }
+        TokenizerKind::Simple => TextAnalyzer::from(SimpleTokenizer)
+            .filter(RemoveLongFilter::limit(40))
+            .filter(LowerCaser),
+    }
+}
+
+/// Check the schema of the existing index, which may be built by older versions or other configs.
+fn check_schema(schema: &Schema) -> Result<()> {
+    // the index built with other languages does not have the language specific fields
+    for lang in Lang::all() {
+        for pf in PostField::lang_fields() {
+            if schema.get_field(&pf.lang_field_name(&lang)).is_none() {
+                return Err(TantivyError::SchemaError(format!(
+                    "The index has no fields for {}. Please run prep with --rebuild",
+                    lang.as_str()
+                )));
+            }
+        }
+    }
+
+    // fields used in filters: category facets, translation links and publication state
+    for pf in PostField::facet_fields().into_iter().chain([
+        PostField::TranslationOf,
+        PostField::Draft,
+        PostField::Unlisted,
+        PostField::PublishAt,
+    ]) {
+        if schema.get_field(pf.as_str()).is_none() {
+            return Err(TantivyError::SchemaError(format!(
+                "The index has no {} field. Please run prep with --rebuild",
+                pf.as_str()
+            )));
+        }
+    }
+
+    // dates are indexed for range queries
+    for pf in [PostField::CreatedAt, PostField::UpdatedAt] {
+        let indexed = schema
+            .get_field(pf.as_str())
+            .map(|field| schema.get_field_entry(field).is_indexed())
+            .unwrap_or(false);
+        if !indexed {
+            return Err(TantivyError::SchemaError(format!(
+                "{} is not indexed in the index. Please run prep with --rebuild",
+                pf.as_str()
+            )));
+        }
+    }
+
+    Ok(())
+}
+
 pub fn read_or_build_index(schema: Schema, index_dir: &Path, rebuild: bool) -> Result<Index> {
     let index = if index_dir.exists() {
         if rebuild {
@@ -36,29 +120,25 @@ pub fn read_or_build_index(schema: Schema, index_dir: &Path, rebuild: bool) -> R
         Index::create_in_dir(index_dir, schema)
     }?;
 
-    let config = TokenizerConfig {
-        dict_path: None,
-        user_dict_path: None,
-        user_dict_bin_path: None,
-        mode: Mode::Decompose(Penalty::default()),
-    };
+    check_schema(&index.schema())?;
 
     index
         .tokenizers()
         .register("whitespace_tokenizer", WhitespaceTokenizer);
     index.tokenizers().register("raw_tokenizer", RawTokenizer);
-    let tokenizer_name = Lang::Ja.tokenizer_name();
-    let ja_tokenizer =
-        TextAnalyzer::from(LinderaTokenizer::with_config(config).unwrap()).filter(LowerCaser);
-    // register Lindera tokenizer
-    index.tokenizers().register(&tokenizer_name, ja_tokenizer);
+    for lang in Lang::all() {
+        index.tokenizers().register(
+            &lang.tokenizer_name(),
+            build_analyzer(lang.tokenizer_kind(config::get())),
+        );
+    }
 
     Ok(index)
 }
 
 #[cfg(test)]
 mod test_index {
-    use super::read_or_build_index;
+    use super::*;
     use crate::text_engine::schema::build_schema;
     use tempdir::TempDir;
 
@@ -77,4 +157,63 @@ mod test_index {
         assert!(read.is_ok());
         assert!(rebuild.is_ok());
     }
+
+    #[test]
+    fn test_read_index_without_lang_fields() {
+        let temp_dir = TempDir::new("test_read_index_without_lang_fields").unwrap();
+        let mut schema_builder = Schema::builder();
+        schema_builder.add_text_field("uuid", STRING | STORED);
+        Index::create_in_dir(temp_dir.path(), schema_builder.build()).unwrap();
+
+        let read = read_or_build_index(build_schema(), temp_dir.path(), false);
+        assert!(matches!(read, Err(TantivyError::SchemaError(_))));
+        let rebuild = read_or_build_index(build_schema(), temp_dir.path(), true);
+        assert!(rebuild.is_ok());
+    }
+
+    #[test]
+    fn test_check_schema() {
+        assert!(check_schema(&build_schema()).is_ok());
+
+        let mut schema_builder = Schema::builder();
+        for lang in Lang::all() {
+            for pf in PostField::lang_fields() {
+                schema_builder.add_text_field(&pf.lang_field_name(&lang), TEXT);
+            }
+        }
+        for pf in [PostField::CreatedAt, PostField::UpdatedAt] {
+            schema_builder.add_date_field(pf.as_str(), FAST | STORED);
+        }
+        let checked = check_schema(&schema_builder.build());
+        assert!(matches!(checked, Err(TantivyError::SchemaError(_))));
+    }
+
+    #[test]
+    fn test_build_analyzer() {
+        let tokens = |kind: TokenizerKind, text: &str| -> Vec<String> {
+            let mut tokens = Vec::new();
+            let analyzer = build_analyzer(kind);
+            let mut stream = analyzer.token_stream(text);
+            while stream.advance() {
+                tokens.push(stream.token().text.clone());
+            }
+            tokens
+        };
+
+        assert_eq!(
+            tokens(
+                TokenizerKind::Stemmer(Language::English),
+                "The Runners are running"
+            ),
+            vec!["runner", "run"]
+        );
+        assert_eq!(
+            tokens(TokenizerKind::Stemmer(Language::German), "Die Häuser"),
+            vec!["die", "haus"]
+        );
+        assert_eq!(
+            tokens(TokenizerKind::Ngram, "한국어"),
+            vec!["한", "한국", "국", "국어", "어"]
+        );
+    }
 }
@@ -1,3 +1,4 @@
 pub mod index;
 pub mod query;
 pub mod schema;
+pub mod snippet;
@@ -1,16 +1,22 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Bound;
 
 use anyhow::{anyhow, Result};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Datelike, Utc};
+use serde::{Deserialize, Serialize};
 
 use tantivy::{
-    collector::{Count, TopDocs},
-    query::{AllQuery, BooleanQuery, Occur, Query, QueryParser, TermQuery},
-    schema::Field,
-    DocAddress, Document, Index, IndexWriter, Term,
+    collector::{Count, DocSetCollector, FacetCollector, TopDocs},
+    query::{
+        AllQuery, BooleanQuery, BoostQuery, MoreLikeThisQuery, Occur, Query, QueryParser,
+        RangeQuery, TermQuery,
+    },
+    schema::{Facet, Field, FieldValue, IndexRecordOption, Schema, Type, Value},
+    DocAddress, DocSet, Document, Index, IndexWriter, Score, Searcher, SegmentOrdinal, Term,
+    TERMINATED,
 };
 
-use crate::posts::Post;
+use crate::posts::{Lang, Post};
 
 use super::schema::{FieldGetter, PostField};
 use crate::datetime::{self, DateTimeFormat, DateTimeWithFormat};
@@ -54,10 +60,12 @@ pub fn get_all(
     Ok(Some(docs))
 }
 
+/// Tags and categories of the published and listed posts.
+/// Tags of a scheduled post are not included until `publish_at`, see `next_publish_at`.
 pub fn get_tags_and_categories(index: &Index) -> Result<(Vec<String>, Vec<String>)> {
-    let q: Box<dyn Query> = Box::new(AllQuery {});
     let schema = index.schema();
     let fg = FieldGetter::new(&schema);
+    let q = Visibility::listing(false).filter(Box::new(AllQuery {}), &fg);
 
     let _docs = get_all(&q, index, None)?;
 
@@ -79,6 +87,233 @@ pub fn get_tags_and_categories(index: &Index) -> Result<(Vec<String>, Vec<String
     Ok((Vec::new(), Vec::new()))
 }
 
+/// Number of posts with a term such as a tag, and the breakdown by language
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct TermCount {
+    pub name: String,
+    pub count: usize,
+    pub langs: BTreeMap<String, usize>,
+}
+
+/// Texts of all terms of `field` in the term dictionaries of the segments
+fn field_terms(searcher: &Searcher, field: Field) -> Result<BTreeSet<String>> {
+    let mut terms = BTreeSet::new();
+    for segment_reader in searcher.segment_readers() {
+        let inverted_index = segment_reader.inverted_index(field)?;
+        let mut stream = inverted_index.terms().stream()?;
+        while let Some((term, _)) = stream.next() {
+            terms.insert(String::from_utf8(term.to_vec())?);
+        }
+    }
+    Ok(terms)
+}
+
+/// Count the posts of each term of `pf`, e.g. each tag, in `lang` or all languages.
+/// Visible posts are searched once per language, and their terms are counted
+/// in a pass of the term dictionaries and postings, so documents are not loaded.
+/// Terms only in deleted or hidden documents are omitted.
+pub fn count_terms(
+    index: &Index,
+    pf: PostField,
+    lang: Option<&Lang>,
+    visibility: Visibility,
+) -> Result<Vec<TermCount>> {
+    let schema = index.schema();
+    let fb = FieldGetter::new(&schema);
+    let searcher = index.reader()?.searcher();
+    let field = fb.get_field(pf);
+    let lang_field = fb.get_field(PostField::Lang);
+    let langs = match lang {
+        Some(lang) => vec![lang.clone()],
+        None => Lang::all(),
+    };
+
+    let mut doc_langs = HashMap::new();
+    for lang in langs.iter() {
+        let q = visibility.filter(
+            Box::new(TermQuery::new(
+                Term::from_field_text(lang_field, lang.as_str()),
+                IndexRecordOption::Basic,
+            )),
+            &fb,
+        );
+        for doc_address in searcher.search(&q, &DocSetCollector)? {
+            doc_langs.insert(doc_address, lang.to_string());
+        }
+    }
+
+    let mut by_term: BTreeMap<String, BTreeMap<String, usize>> = BTreeMap::new();
+    for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
+        let inverted_index = segment_reader.inverted_index(field)?;
+        let mut stream = inverted_index.terms().stream()?;
+        while let Some((term, term_info)) = stream.next() {
+            let mut postings =
+                inverted_index.read_postings_from_terminfo(term_info, IndexRecordOption::Basic)?;
+            let mut doc_id = postings.doc();
+            while doc_id != TERMINATED {
+                let doc_address = DocAddress::new(segment_ord as SegmentOrdinal, doc_id);
+                if let Some(lang) = doc_langs.get(&doc_address) {
+                    *by_term
+                        .entry(String::from_utf8(term.to_vec())?)
+                        .or_default()
+                        .entry(lang.clone())
+                        .or_default() += 1;
+                }
+                doc_id = postings.advance();
+            }
+        }
+    }
+
+    Ok(by_term
+        .into_iter()
+        .map(|(name, by_lang)| TermCount {
+            name,
+            count: by_lang.values().sum(),
+            langs: by_lang,
+        })
+        .collect())
+}
+
+/// Node of the category hierarchy.
+/// `count` is the number of posts in the category and its descendants.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct CategoryNode {
+    pub name: String,
+    /// facet path such as `/programming/rust`, which is accepted as `category` filter
+    pub path: String,
+    pub count: u64,
+    pub children: Vec<CategoryNode>,
+}
+
+/// Hierarchy of the categories of the posts in `lang` or all languages, counted by facets.
+pub fn category_tree(
+    index: &Index,
+    lang: Option<&Lang>,
+    visibility: Visibility,
+) -> Result<Vec<CategoryNode>> {
+    let schema = index.schema();
+    let fb = FieldGetter::new(&schema);
+    let searcher = index.reader()?.searcher();
+    let field = fb.get_field(PostField::CategoryFacet);
+    let query: Box<dyn Query> = match lang {
+        Some(lang) => Box::new(TermQuery::new(
+            Term::from_field_text(fb.get_field(PostField::Lang), lang.as_str()),
+            IndexRecordOption::Basic,
+        )),
+        None => Box::new(AllQuery {}),
+    };
+    let query = visibility.filter(query, &fb);
+
+    // a facet collector counts the children of the facets which are not prefixes of each other,
+    // so the children are counted by depth
+    let mut facets_by_depth: BTreeMap<usize, Vec<Facet>> = BTreeMap::new();
+    facets_by_depth.insert(0, vec![Facet::root()]);
+    for term in field_terms(&searcher, field)? {
+        let facet = Facet::from_encoded(term.into_bytes())?;
+        if !facet.is_root() {
+            facets_by_depth
+                .entry(facet.to_path().len())
+                .or_default()
+                .push(facet);
+        }
+    }
+
+    let mut children: HashMap<Facet, Vec<(Facet, u64)>> = HashMap::new();
+    for facets in facets_by_depth.into_values() {
+        let mut collector = FacetCollector::for_field(field);
+        for facet in facets.iter() {
+            collector.add_facet(facet.clone());
+        }
+        let counts = searcher.search(&query, &collector)?;
+        for facet in facets.into_iter() {
+            let facet_children = counts
+                .get(facet.clone())
+                .filter(|(_, count)| *count > 0)
+                .map(|(child, count)| (child.clone(), count))
+                .collect();
+            children.insert(facet, facet_children);
+        }
+    }
+
+    fn build(facet: &Facet, children: &HashMap<Facet, Vec<(Facet, u64)>>) -> Vec<CategoryNode> {
+        children
+            .get(facet)
+            .map(|facet_children| {
+                facet_children
+                    .iter()
+                    .map(|(child, count)| CategoryNode {
+                        name: child.to_path().last().unwrap_or(&"").to_string(),
+                        path: child.to_string(),
+                        count: *count,
+                        children: build(child, children),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+    Ok(build(&Facet::root(), &children))
+}
+
+/// Posts created in a month. `docs` are empty unless they are requested.
+#[derive(Debug)]
+pub struct ArchiveMonth {
+    pub year: i32,
+    pub month: u32,
+    pub count: usize,
+    pub docs: Vec<Document>,
+}
+
+/// Posts matched by `query` grouped by year and month of `created_at` in UTC, newest first.
+/// Only the dates in the fast field are read unless `with_docs` is set.
+pub fn get_archive(query: &dyn Query, index: &Index, with_docs: bool) -> Result<Vec<ArchiveMonth>> {
+    let schema = index.schema();
+    let fb = FieldGetter::new(&schema);
+    let searcher = index.reader()?.searcher();
+    let count = searcher.search(query, &Count {})?;
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+
+    let collector =
+        TopDocs::with_limit(count).order_by_fast_field(fb.get_field(PostField::CreatedAt));
+    let dates: Vec<(DateTime<Utc>, DocAddress)> = searcher.search(query, &collector)?;
+
+    let mut archive: Vec<ArchiveMonth> = Vec::new();
+    for (created_at, doc_address) in dates.into_iter() {
+        let (year, month) = (created_at.year(), created_at.month());
+        let is_same_month =
+            matches!(archive.last(), Some(last) if (last.year, last.month) == (year, month));
+        if !is_same_month {
+            archive.push(ArchiveMonth {
+                year,
+                month,
+                count: 0,
+                docs: Vec::new(),
+            });
+        }
+        let last = archive.last_mut().unwrap();
+        last.count += 1;
+        if with_docs {
+            last.docs.push(searcher.doc(doc_address)?);
+        }
+    }
+    Ok(archive)
+}
+
+pub fn get_all_by_uuid(index: &Index) -> Result<HashMap<String, Document>> {
+    let q: Box<dyn Query> = Box::new(AllQuery {});
+    let schema = index.schema();
+    let fg = FieldGetter::new(&schema);
+
+    match get_all(&q, index, None)? {
+        Some(docs) => docs
+            .into_iter()
+            .map(|doc| Ok((fg.get_text(&doc, PostField::Uuid)?, doc)))
+            .collect(),
+        None => Ok(HashMap::new()),
+    }
+}
+
 pub fn term_query_one(term: &str, field: Field, index: &Index) -> Result<Document> {
     let reader = index.reader()?;
     let seracher = reader.searcher();
@@ -132,22 +367,124 @@ pub fn get_by_slug_with_lang(slug: &str, lang: &str, index: &Index) -> Result<Do
     Ok(searcher.doc(doc_address)?)
 }
 
-pub fn put(
-    post: &Post,
+/// Posts of the other languages linked to the post, ordered by lang.
+/// Posts sharing the slug, the original post of `translation_of`
+/// and the other translations of the original are linked.
+pub fn get_translations(
+    doc: &Document,
     index: &Index,
-    index_writer: &mut IndexWriter,
+    visibility: Visibility,
+) -> Result<Vec<Document>> {
+    let schema = index.schema();
+    let fb = FieldGetter::new(&schema);
+    let term = |pf: PostField, text: &str| -> Box<dyn Query> {
+        Box::new(TermQuery::new(
+            Term::from_field_text(fb.get_field(pf), text),
+            IndexRecordOption::Basic,
+        ))
+    };
+
+    let original = match fb.get_text(doc, PostField::TranslationOf)? {
+        translation_of if translation_of.is_empty() => fb.get_text(doc, PostField::Uuid)?,
+        translation_of => translation_of,
+    };
+    let linked = BooleanQuery::new(vec![
+        (
+            Occur::Should,
+            term(PostField::Slug, &fb.get_text(doc, PostField::Slug)?),
+        ),
+        (Occur::Should, term(PostField::Uuid, &original)),
+        (Occur::Should, term(PostField::TranslationOf, &original)),
+    ]);
+    let mut queries: Vec<(Occur, Box<dyn Query>)> = vec![
+        (Occur::Must, Box::new(linked)),
+        (
+            Occur::MustNot,
+            term(PostField::Lang, &fb.get_text(doc, PostField::Lang)?),
+        ),
+    ];
+    queries.extend(visibility.queries(&fb));
+    let q = BooleanQuery::new(queries);
+
+    let mut translations = BTreeMap::new();
+    for doc in get_all(&q, index, None)?.unwrap_or_default() {
+        translations
+            .entry(fb.get_text(&doc, PostField::Lang)?)
+            .or_insert(doc);
+    }
+    Ok(translations.into_values().collect())
+}
+
+/// The post of `slug` in `lang`, or its translation in `lang` with another slug.
+/// If the post in `lang` is missing, the post or its translation in the default language.
+/// Posts hidden by `visibility` are regarded as not found.
+/// The flag is true if the post in the default language is returned for the missing `lang`.
+pub fn get_by_slug_with_fallback(
+    slug: &str,
+    lang: &str,
+    index: &Index,
+    visibility: Visibility,
+) -> Result<(Document, bool)> {
+    let schema = index.schema();
+    let fb = FieldGetter::new(&schema);
+    let visible = |doc: Document| -> Result<Document> {
+        match visibility.allows(&doc, &fb)? {
+            true => Ok(doc),
+            false => Err(anyhow!("slug: {} and lang: {} is Not Found", slug, lang)),
+        }
+    };
+
+    if let Ok(doc) = get_by_slug_with_lang(slug, lang, index).and_then(visible) {
+        return Ok((doc, false));
+    }
+
+    let translations = match term_query_one(slug, fb.get_field(PostField::Slug), index) {
+        Ok(doc) => get_translations(&doc, index, visibility)?,
+        Err(_) => Vec::new(),
+    };
+    let translation_in = |lang: &str| -> Result<Option<Document>> {
+        for doc in translations.iter() {
+            if fb.get_text(doc, PostField::Lang)? == lang {
+                return Ok(Some(doc.clone()));
+            }
+        }
+        Ok(None)
+    };
+    if let Some(doc) = translation_in(lang)? {
+        return Ok((doc, false));
+    }
+
+    let default_lang = Lang::default_lang().to_string();
+    if lang != default_lang {
+        if let Ok(doc) = get_by_slug_with_lang(slug, &default_lang, index).and_then(visible) {
+            return Ok((doc, true));
+        }
+        if let Some(doc) = translation_in(&default_lang)? {
+            return Ok((doc, true));
+        }
+    }
+    Err(anyhow!("slug: {} and lang: {} is Not Found", slug, lang))
+}
+
+/// Stage the add or update of `post` on `index_writer` without committing.
+/// `prev_doc` is the document indexed with the same uuid, if any.
+/// Returns `None` if the post has no changes from `prev_doc`.
+pub fn stage(
+    post: &Post,
+    prev_doc: Option<&Document>,
+    schema: &Schema,
+    index_writer: &IndexWriter,
     skip_update_date: bool,
 ) -> Result<Option<Document>> {
     let now = Utc::now();
 
-    let schema = index.schema();
-    let fb = FieldGetter::new(&schema);
-    let new_doc = match get_by_uuid(&post.uuid(), index) {
-        Ok(doc) => {
+    let fb = FieldGetter::new(schema);
+    let new_doc = match prev_doc {
+        Some(doc) => {
             let uuid_field = fb.get_field(PostField::Uuid);
             // if no update in post, skip update index
             // post.diff(&Post::from_doc(&doc, &schema)?);
-            if post.equal_from_doc(&Post::from_doc(&doc, &schema)?) {
+            if post.equal_from_doc(&Post::from_doc(doc, schema)?) {
                 info!("skip post: {}", post.title());
                 return Ok(None);
             }
@@ -155,8 +492,8 @@ pub fn put(
             let created_at = if let Some(created_at) = post.matter().created_at() {
                 created_at
             } else {
-                let datetime = fb.get_date(&doc, PostField::CreatedAt)?;
-                let format = fb.get_text(&doc, PostField::CreatedAtFormat)?;
+                let datetime = fb.get_date(doc, PostField::CreatedAt)?;
+                let format = fb.get_text(doc, PostField::CreatedAtFormat)?;
                 DateTimeWithFormat::new(datetime, DateTimeFormat::from(format.as_str()))
             };
 
@@ -164,16 +501,16 @@ pub fn put(
                 post.updated_at().unwrap()
             } else {
                 let updated_at_format =
-                    DateTimeFormat::from(fb.get_text(&doc, PostField::UpdatedAtFormat)?.as_str());
+                    DateTimeFormat::from(fb.get_text(doc, PostField::UpdatedAtFormat)?.as_str());
                 DateTimeWithFormat::new(now, updated_at_format)
             };
 
-            let new_doc = post.to_doc(&schema, &created_at, &updated_at);
+            let new_doc = post.to_doc(schema, &created_at, &updated_at);
             index_writer.delete_term(Term::from_field_text(uuid_field, &post.uuid()));
             index_writer.add_document(new_doc.clone());
             new_doc
         }
-        Err(_) => {
+        None => {
             // If no document in index, insert doc
             let now_with_format = DateTimeWithFormat::new(now, DateTimeFormat::RFC3339);
             let created_at = if let Some(c) = post.created_at() {
@@ -188,34 +525,422 @@ pub fn put(
                 now_with_format
             };
 
-            let new_doc = post.to_doc(&index.schema(), &created_at, &updated_at);
+            let new_doc = post.to_doc(schema, &created_at, &updated_at);
             index_writer.add_document(new_doc.clone());
             new_doc
         }
     };
-    index_writer.commit()?;
     Ok(Some(new_doc))
 }
 
-pub fn search(
-    query: &str,
-    fields: Vec<Field>,
-    limit: usize,
+/// Stage the post and commit it at once, which is only used by tests.
+/// `prep` stages all posts with `stage` and commits them once.
+#[cfg(test)]
+pub fn put(
+    post: &Post,
     index: &Index,
-) -> Result<Vec<Document>> {
-    if limit == 0 {
-        return Ok(Vec::new());
+    index_writer: &mut IndexWriter,
+    skip_update_date: bool,
+) -> Result<Option<Document>> {
+    let prev_doc = get_by_uuid(&post.uuid(), index).ok();
+    let new_doc = stage(
+        post,
+        prev_doc.as_ref(),
+        &index.schema(),
+        index_writer,
+        skip_update_date,
+    )?;
+
+    if new_doc.is_some() {
+        index_writer.commit()?;
     }
-    let searcher = index.reader()?.searcher();
-    let query_parser = QueryParser::for_index(index, fields);
-    let query = query_parser.parse_query(query)?;
+    Ok(new_doc)
+}
+
+/// Stage the delete of the post with `uuid` on `index_writer` without committing.
+pub fn stage_delete(uuid: &str, schema: &Schema, index_writer: &IndexWriter) {
+    let fb = FieldGetter::new(schema);
+    info!("delete post: {}", uuid);
+    index_writer.delete_term(Term::from_field_text(fb.get_field(PostField::Uuid), uuid));
+}
 
-    let docs = searcher.search(&query, &TopDocs::with_limit(limit))?;
+/// Addresses of documents with the scores if they are computed
+type PageAddresses = Vec<(Option<Score>, DocAddress)>;
 
-    Ok(docs
+/// Collect the addresses of the page of `limit` documents from `offset` with the total count of hits.
+/// Scores are only given if the page is ordered by relevance.
+fn get_page_addresses(
+    query: &dyn Query,
+    searcher: &Searcher,
+    fb: &FieldGetter,
+    order_by: Option<datetime::OrderBy>,
+    order: datetime::Order,
+    offset: usize,
+    limit: usize,
+) -> Result<(usize, PageAddresses)> {
+    let total = searcher.search(query, &Count {})?;
+    if offset >= total || limit == 0 {
+        return Ok((total, Vec::new()));
+    }
+    let limit = limit.min(total - offset);
+
+    // TopDocs only collects in descending order,
+    // so the page in ascending order is collected from the tail and reversed
+    let (desc_offset, desc_limit) = match order {
+        datetime::Order::Desc => (offset, limit),
+        datetime::Order::Asc => {
+            let end = total - offset;
+            let start = end.saturating_sub(limit);
+            (start, end - start)
+        }
+    };
+    let top_docs = TopDocs::with_limit(desc_limit).and_offset(desc_offset);
+
+    let mut doc_addresses: PageAddresses = if let Some(order_by) = order_by {
+        let field = match order_by {
+            datetime::OrderBy::CreatedAt => fb.get_field(PostField::CreatedAt),
+            datetime::OrderBy::UpdatedAt => fb.get_field(PostField::UpdatedAt),
+        };
+        searcher
+            .search(query, &top_docs.order_by_fast_field(field))?
+            .into_iter()
+            .map(|doc: (DateTime<Utc>, DocAddress)| (None, doc.1))
+            .collect()
+    } else {
+        searcher
+            .search(query, &top_docs)?
+            .into_iter()
+            .map(|(score, doc_address)| (Some(score), doc_address))
+            .collect()
+    };
+
+    if let datetime::Order::Asc = order {
+        doc_addresses.reverse();
+    }
+
+    Ok((total, doc_addresses))
+}
+
+/// Collect the page of `limit` documents from `offset` with the total count of hits.
+/// The page is collected in `TopDocs`, so the documents out of the page are not loaded.
+pub fn get_page(
+    query: &dyn Query,
+    index: &Index,
+    order_by: Option<datetime::OrderBy>,
+    order: datetime::Order,
+    offset: usize,
+    limit: usize,
+) -> Result<(usize, Vec<Document>)> {
+    let schema = index.schema();
+    let searcher = index.reader()?.searcher();
+    let fb = FieldGetter::new(&schema);
+
+    let (total, doc_addresses) =
+        get_page_addresses(query, &searcher, &fb, order_by, order, offset, limit)?;
+    let docs = doc_addresses
         .into_iter()
         .flat_map(|(_, doc_address)| searcher.doc(doc_address).ok())
-        .collect())
+        .collect();
+
+    Ok((total, docs))
+}
+
+/// Same as `get_page`, but each document comes with its relevance score.
+/// The documents are ordered by relevance if `order_by` is `None`.
+/// Otherwise the scores of the documents in the page are computed afterwards.
+pub fn get_scored_page(
+    query: &dyn Query,
+    index: &Index,
+    order_by: Option<datetime::OrderBy>,
+    order: datetime::Order,
+    offset: usize,
+    limit: usize,
+) -> Result<(usize, Vec<(Score, Document)>)> {
+    let schema = index.schema();
+    let searcher = index.reader()?.searcher();
+    let fb = FieldGetter::new(&schema);
+
+    let (total, doc_addresses) =
+        get_page_addresses(query, &searcher, &fb, order_by, order, offset, limit)?;
+    let weight = query.weight(&searcher, true)?;
+    let docs = doc_addresses
+        .into_iter()
+        .map(|(score, doc_address)| {
+            let score = match score {
+                Some(score) => score,
+                None => weight
+                    .explain(
+                        searcher.segment_reader(doc_address.segment_ord),
+                        doc_address.doc_id,
+                    )?
+                    .value(),
+            };
+            Ok((score, searcher.doc(doc_address)?))
+        })
+        .collect::<Result<_>>()?;
+
+    Ok((total, docs))
+}
+
+/// Boost of each tag shared with the post in `related_query`
+const RELATED_TAG_BOOST: Score = 2.0;
+
+/// Query of the posts related to `doc` in the same language, excluding `doc` itself.
+/// Posts are ranked by shared tags, the same category and the similarity of raw text.
+pub fn related_query(doc: &Document, index: &Index) -> Result<Box<dyn Query>> {
+    let schema = index.schema();
+    let fb = FieldGetter::new(&schema);
+    let term_query = |pf: PostField, text: &str| -> Box<dyn Query> {
+        Box::new(TermQuery::new(
+            Term::from_field_text(fb.get_field(pf), text),
+            IndexRecordOption::Basic,
+        ))
+    };
+
+    let mut similarities: Vec<(Occur, Box<dyn Query>)> = fb
+        .get_tags(doc)?
+        .iter()
+        .map(|tag| {
+            let q: Box<dyn Query> = Box::new(BoostQuery::new(
+                term_query(PostField::Tags, tag),
+                RELATED_TAG_BOOST,
+            ));
+            (Occur::Should, q)
+        })
+        .collect();
+    similarities.push((
+        Occur::Should,
+        term_query(PostField::Category, &fb.get_text(doc, PostField::Category)?),
+    ));
+
+    let lang = Lang::from_str(&fb.get_text(doc, PostField::Lang)?)?;
+    let raw_text = fb.get_text(doc, PostField::RawText)?;
+    if !raw_text.trim().is_empty() {
+        // language specific fields are not stored, so the terms are taken from the stored text
+        let field = fb.get_lang_field(PostField::RawText, &lang);
+        let more_like_this = MoreLikeThisQuery::builder()
+            .with_min_doc_frequency(1)
+            .with_min_term_frequency(1)
+            .with_document_fields(vec![(
+                field,
+                vec![FieldValue::new(field, Value::Str(raw_text))],
+            )]);
+        similarities.push((Occur::Should, Box::new(more_like_this)));
+    }
+
+    Ok(Box::new(BooleanQuery::new(vec![
+        (Occur::Must, term_query(PostField::Lang, lang.as_str())),
+        (
+            Occur::MustNot,
+            term_query(PostField::Uuid, &fb.get_text(doc, PostField::Uuid)?),
+        ),
+        (Occur::Must, Box::new(BooleanQuery::new(similarities))),
+    ])))
+}
+
+/// Query of the documents with the date of `field` in the range of `left` and `right`
+pub fn date_range_query(
+    field: Field,
+    left: Bound<DateTime<Utc>>,
+    right: Bound<DateTime<Utc>>,
+) -> Box<dyn Query> {
+    let to_term = |bound: Bound<DateTime<Utc>>| match bound {
+        Bound::Included(date) => Bound::Included(Term::from_field_date(field, &date)),
+        Bound::Excluded(date) => Bound::Excluded(Term::from_field_date(field, &date)),
+        Bound::Unbounded => Bound::Unbounded,
+    };
+    Box::new(RangeQuery::new_term_bounds(
+        field,
+        Type::Date,
+        &to_term(left),
+        &to_term(right),
+    ))
+}
+
+/// Posts served for a request.
+/// Drafts and posts scheduled by `publish_at` are hidden unless `preview`,
+/// and unlisted posts are hidden from listings but resolved by slug or uuid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Visibility {
+    pub preview: bool,
+    pub listed: bool,
+}
+
+impl Visibility {
+    /// posts in listings such as `/posts`, search, feeds and counts
+    pub fn listing(preview: bool) -> Self {
+        Self {
+            preview,
+            listed: true,
+        }
+    }
+
+    /// posts resolved by slug or uuid
+    pub fn single(preview: bool) -> Self {
+        Self {
+            preview,
+            listed: false,
+        }
+    }
+
+    /// `MustNot` clauses of the hidden posts, which should be combined with a `Must` clause.
+    pub fn queries(&self, fb: &FieldGetter) -> Vec<(Occur, Box<dyn Query>)> {
+        let flag = |pf: PostField| -> Box<dyn Query> {
+            Box::new(TermQuery::new(
+                Term::from_field_text(fb.get_field(pf), "true"),
+                IndexRecordOption::Basic,
+            ))
+        };
+
+        let mut queries = Vec::new();
+        if !self.preview {
+            queries.push((Occur::MustNot, flag(PostField::Draft)));
+            queries.push((
+                Occur::MustNot,
+                date_range_query(
+                    fb.get_field(PostField::PublishAt),
+                    Bound::Excluded(Utc::now()),
+                    Bound::Unbounded,
+                ),
+            ));
+        }
+        if self.listed {
+            queries.push((Occur::MustNot, flag(PostField::Unlisted)));
+        }
+        queries
+    }
+
+    /// `query` without the hidden posts
+    pub fn filter(&self, query: Box<dyn Query>, fb: &FieldGetter) -> Box<dyn Query> {
+        let mut queries = vec![(Occur::Must, query)];
+        queries.extend(self.queries(fb));
+        Box::new(BooleanQuery::new(queries))
+    }
+
+    pub fn allows(&self, doc: &Document, fb: &FieldGetter) -> Result<bool> {
+        if !self.preview {
+            if fb.get_text(doc, PostField::Draft)? == "true" {
+                return Ok(false);
+            }
+            if let Some(publish_at) = fb.get_optional_date(doc, PostField::PublishAt) {
+                if publish_at > Utc::now() {
+                    return Ok(false);
+                }
+            }
+        }
+        Ok(!(self.listed && fb.get_text(doc, PostField::Unlisted)? == "true"))
+    }
+}
+
+/// The earliest `publish_at` of the scheduled posts to be listed.
+/// Tags and categories computed before it are outdated after it.
+pub fn next_publish_at(index: &Index) -> Result<Option<DateTime<Utc>>> {
+    let schema = index.schema();
+    let fb = FieldGetter::new(&schema);
+    let scheduled = date_range_query(
+        fb.get_field(PostField::PublishAt),
+        Bound::Excluded(Utc::now()),
+        Bound::Unbounded,
+    );
+    let mut queries = vec![(Occur::Must, scheduled)];
+    // drafts are not published by the date
+    queries.extend(Visibility::listing(true).queries(&fb).into_iter().chain([(
+        Occur::MustNot,
+        Box::new(TermQuery::new(
+            Term::from_field_text(fb.get_field(PostField::Draft), "true"),
+            IndexRecordOption::Basic,
+        )) as Box<dyn Query>,
+    )]));
+
+    Ok(get_all(&BooleanQuery::new(queries), index, None)?
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|doc| fb.get_optional_date(doc, PostField::PublishAt))
+        .min())
+}
+
+/// The previous and next posts of `doc` by `created_at` in the posts matched by `filters`.
+/// Posts are ordered by `(created_at, uuid)`, so posts created at the same time, e.g. on the same day,
+/// are also adjacent to each other.
+pub fn get_adjacent(
+    doc: &Document,
+    filters: &[(Occur, Box<dyn Query>)],
+    index: &Index,
+) -> Result<(Option<Document>, Option<Document>)> {
+    let schema = index.schema();
+    let fb = FieldGetter::new(&schema);
+    let field = fb.get_field(PostField::CreatedAt);
+    let created_at = fb.get_date(doc, PostField::CreatedAt)?;
+    let uuid = fb.get_text(doc, PostField::Uuid)?;
+
+    let filtered = |range: Box<dyn Query>| -> BooleanQuery {
+        let mut queries: Vec<(Occur, Box<dyn Query>)> = filters
+            .iter()
+            .map(|(occur, q)| (*occur, q.box_clone()))
+            .collect();
+        queries.push((Occur::Must, range));
+        BooleanQuery::new(queries)
+    };
+    // posts created at `date` ordered by uuid
+    let created_at_same = |date: DateTime<Utc>| -> Result<Vec<(String, Document)>> {
+        let query = filtered(date_range_query(
+            field,
+            Bound::Included(date),
+            Bound::Included(date),
+        ));
+        let mut docs = get_all(&query, index, None)?
+            .unwrap_or_default()
+            .into_iter()
+            .map(|doc| Ok((fb.get_text(&doc, PostField::Uuid)?, doc)))
+            .collect::<Result<Vec<_>>>()?;
+        docs.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(docs)
+    };
+    let nearest_date =
+        |range: Box<dyn Query>, order: datetime::Order| -> Result<Option<DateTime<Utc>>> {
+            let (_, docs) = get_page(
+                &filtered(range),
+                index,
+                Some(datetime::OrderBy::CreatedAt),
+                order,
+                0,
+                1,
+            )?;
+            docs.first()
+                .map(|doc| fb.get_date(doc, PostField::CreatedAt))
+                .transpose()
+        };
+
+    let same = created_at_same(created_at)?;
+    let prev = match same.iter().rev().find(|(u, _)| *u < uuid) {
+        Some((_, doc)) => Some(doc.clone()),
+        None => match nearest_date(
+            date_range_query(field, Bound::Unbounded, Bound::Excluded(created_at)),
+            datetime::Order::Desc,
+        )? {
+            Some(date) => created_at_same(date)?.pop().map(|(_, doc)| doc),
+            None => None,
+        },
+    };
+    let next = match same.iter().find(|(u, _)| *u > uuid) {
+        Some((_, doc)) => Some(doc.clone()),
+        None => match nearest_date(
+            date_range_query(field, Bound::Excluded(created_at), Bound::Unbounded),
+            datetime::Order::Asc,
+        )? {
+            Some(date) => created_at_same(date)?
+                .into_iter()
+                .next()
+                .map(|(_, doc)| doc),
+            None => None,
+        },
+    };
+    Ok((prev, next))
+}
+
+pub fn parse_query(query: &str, fields: Vec<Field>, index: &Index) -> Result<Box<dyn Query>> {
+    let query_parser = QueryParser::for_index(index, fields);
+    Ok(query_parser.parse_query(query)?)
 }
 
 #[cfg(test)]
@@ -223,7 +948,7 @@ mod test {
     use crate::test_utility::*;
 
     use super::*;
-    use crate::posts::frontmatter::FrontMatter;
+    use crate::posts::frontmatter::{FrontMatter, Publication};
     use crate::test_utility::build_random_posts_index;
     use crate::{
         posts::Post,
@@ -247,6 +972,459 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_get_page() -> Result<()> {
+        let temp_dir = TempDir::new(&format!("temp_rand_index_{}", uuid::Uuid::new_v4()))?;
+        let (_, index) = build_random_posts_index(10, temp_dir.path())?;
+        let schema = index.schema();
+        let fg = FieldGetter::new(&schema);
+        let q: Box<dyn Query> = Box::new(AllQuery {});
+        let order_by = Some(datetime::OrderBy::CreatedAt);
+
+        let uuids = |docs: Vec<Document>| -> Vec<String> {
+            docs.iter()
+                .map(|doc| fg.get_text(doc, PostField::Uuid).unwrap())
+                .collect()
+        };
+
+        let (total, desc) = get_page(&q, &index, order_by, datetime::Order::Desc, 0, 10)?;
+        assert_eq!(total, 10);
+        let desc = uuids(desc);
+        let mut asc = desc.clone();
+        asc.reverse();
+
+        for (order, expected) in [(datetime::Order::Desc, &desc), (datetime::Order::Asc, &asc)] {
+            let (total, page) = get_page(&q, &index, order_by, order, 3, 4)?;
+            assert_eq!(total, 10);
+            assert_eq!(uuids(page), expected[3..7].to_vec());
+
+            let (_, last_page) = get_page(&q, &index, order_by, order, 8, 4)?;
+            assert_eq!(uuids(last_page), expected[8..].to_vec());
+        }
+
+        let (total, out_of_range) = get_page(&q, &index, order_by, datetime::Order::Desc, 10, 4)?;
+        assert_eq!(total, 10);
+        assert!(out_of_range.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_scored_page() -> Result<()> {
+        let temp_dir = TempDir::new(&format!("temp_rand_index_{}", uuid::Uuid::new_v4()))?;
+        let (mut posts, index) = build_random_posts_index(5, temp_dir.path())?;
+        let schema = index.schema();
+        let fg = FieldGetter::new(&schema);
+
+        *posts[0].body_mut() = "alpha beta".to_string();
+        *posts[1].body_mut() = "alpha alpha alpha".to_string();
+        let mut index_writer = index.writer(100_000_000)?;
+        for post in posts[..2].iter() {
+            put(post, &index, &mut index_writer, false)?;
+        }
+        let q = parse_query("alpha", vec![fg.get_field(PostField::Body)], &index)?;
+
+        let (total, by_relevance) =
+            get_scored_page(&*q, &index, None, datetime::Order::Desc, 0, 10)?;
+        assert_eq!(total, 2);
+        assert_eq!(
+            fg.get_text(&by_relevance[0].1, PostField::Uuid)?,
+            posts[1].uuid()
+        );
+        assert!(by_relevance[0].0 > by_relevance[1].0);
+
+        // scores do not depend on the order
+        let (_, by_date) = get_scored_page(
+            &*q,
+            &index,
+            Some(datetime::OrderBy::UpdatedAt),
+            datetime::Order::Asc,
+            0,
+            10,
+        )?;
+        for (score, doc) in by_date.iter() {
+            let uuid = fg.get_text(doc, PostField::Uuid)?;
+            let (expected, _) = by_relevance
+                .iter()
+                .find(|(_, d)| fg.get_text(d, PostField::Uuid).unwrap() == uuid)
+                .unwrap();
+            assert!((score - expected).abs() < f32::EPSILON);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_related_query() -> Result<()> {
+        let temp_dir = TempDir::new(&format!("temp_rand_index_{}", uuid::Uuid::new_v4()))?;
+        let (_, index) = build_random_posts_index(3, temp_dir.path())?;
+        let schema = index.schema();
+        let fg = FieldGetter::new(&schema);
+        let mut index_writer = index.writer(100_000_000)?;
+
+        let post = |lang: &str, category: &str, tags: &[&str], body: &str| -> Result<Post> {
+            let matter = FrontMatter::new(
+                uuid::Uuid::new_v4(),
+                "title",
+                "description",
+                category,
+                Lang::from_str(lang)?,
+                Some(tags.iter().map(|t| t.to_string()).collect()),
+                None,
+                None,
+            );
+            Ok(Post::new(rand_alpahbet(10), matter, body.to_string()))
+        };
+        let target = post("en", "rust", &["async", "tokio"], "running tokio executors")?;
+        let same_tags_and_category = post("en", "rust", &["async", "tokio"], "nothing")?;
+        let same_category = post("en", "rust", &[], "nothing")?;
+        let similar_text = post("en", "python", &[], "an executor is running")?;
+        let unrelated = post("en", "python", &["django"], "nothing")?;
+        let other_lang = post("ja", "rust", &["async", "tokio"], "nothing")?;
+        for p in [
+            &target,
+            &same_tags_and_category,
+            &same_category,
+            &similar_text,
+            &unrelated,
+            &other_lang,
+        ] {
+            put(p, &index, &mut index_writer, false)?;
+        }
+
+        let query = related_query(&get_by_uuid(&target.uuid(), &index)?, &index)?;
+        let (total, docs) = get_page(&*query, &index, None, datetime::Order::Desc, 0, 10)?;
+        let uuids: Vec<String> = docs
+            .iter()
+            .map(|doc| fg.get_text(doc, PostField::Uuid).unwrap())
+            .collect();
+        assert_eq!(total, 3);
+        assert_eq!(uuids[0], same_tags_and_category.uuid());
+        assert!(uuids.contains(&same_category.uuid()));
+        assert!(uuids.contains(&similar_text.uuid()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_count_terms() -> Result<()> {
+        let temp_dir = TempDir::new(&format!("temp_rand_index_{}", uuid::Uuid::new_v4()))?;
+        let (posts, index) = build_random_posts_index(10, temp_dir.path())?;
+
+        let counts = count_terms(
+            &index,
+            PostField::Category,
+            None,
+            Visibility::listing(false),
+        )?;
+        assert_eq!(counts.iter().map(|c| c.count).sum::<usize>(), 10);
+        for c in counts.iter() {
+            let expected = posts.iter().filter(|p| p.category() == c.name).count();
+            assert_eq!(c.count, expected);
+        }
+
+        let en = Lang::from_str("en")?;
+        let counts = count_terms(
+            &index,
+            PostField::Tags,
+            Some(&en),
+            Visibility::listing(false),
+        )?;
+        for c in counts.iter() {
+            let expected = posts
+                .iter()
+                .filter(|p| p.lang() == en && p.tags().unwrap_or_default().contains(&c.name))
+                .count();
+            assert_eq!(c.count, expected);
+            assert_eq!(c.langs.keys().collect::<Vec<_>>(), vec!["en"]);
+        }
+
+        // deleted and hidden posts are not counted
+        let mut index_writer = index.writer(100_000_000)?;
+        stage_delete(&posts[0].uuid(), &index.schema(), &index_writer);
+        index_writer.commit()?;
+        let draft = Post::new(
+            rand_alpahbet(10),
+            rand_matter().with_publication(Publication {
+                draft: true,
+                ..Publication::default()
+            }),
+            "body".to_string(),
+        );
+        put(&draft, &index, &mut index_writer, false)?;
+        let counts = count_terms(
+            &index,
+            PostField::Category,
+            None,
+            Visibility::listing(false),
+        )?;
+        assert_eq!(counts.iter().map(|c| c.count).sum::<usize>(), 9);
+        let counts = count_terms(&index, PostField::Category, None, Visibility::listing(true))?;
+        assert_eq!(counts.iter().map(|c| c.count).sum::<usize>(), 10);
+        Ok(())
+    }
+
+    #[test]
+    fn test_category_tree() -> Result<()> {
+        let temp_dir = TempDir::new(&format!("temp_rand_index_{}", uuid::Uuid::new_v4()))?;
+        let index = read_or_build_index(build_schema(), temp_dir.path(), true)?;
+        let mut index_writer = index.writer(100_000_000)?;
+        for (lang, category) in [
+            ("en", "programming/rust/async"),
+            ("en", "programming/rust"),
+            ("ja", "programming/python"),
+            ("en", "life"),
+        ] {
+            put_post_with(
+                &index,
+                &mut index_writer,
+                category,
+                Lang::from_str(lang)?,
+                None,
+                None,
+            )?;
+        }
+
+        let tree = category_tree(&index, None, Visibility::listing(false))?;
+        assert_eq!(
+            tree.iter()
+                .map(|n| (n.path.as_str(), n.count))
+                .collect::<Vec<_>>(),
+            vec![("/life", 1), ("/programming", 3)]
+        );
+        let programming = &tree[1];
+        assert_eq!(programming.name, "programming");
+        assert_eq!(
+            programming
+                .children
+                .iter()
+                .map(|n| (n.name.as_str(), n.count))
+                .collect::<Vec<_>>(),
+            vec![("python", 1), ("rust", 2)]
+        );
+        assert_eq!(
+            programming.children[1].children[0].path,
+            "/programming/rust/async"
+        );
+
+        let tree = category_tree(
+            &index,
+            Some(&Lang::from_str("ja")?),
+            Visibility::listing(false),
+        )?;
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree[0].children[0].name, "python");
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_translations() -> Result<()> {
+        let temp_dir = TempDir::new("test_get_translations")?;
+        let (_, index) = build_random_posts_index(3, temp_dir.path())?;
+        let schema = index.schema();
+        let fg = FieldGetter::new(&schema);
+        let mut index_writer = index.writer(100_000_000)?;
+        let (ja, en) = (Lang::from_str("ja")?, Lang::from_str("en")?);
+        let visibility = Visibility::single(false);
+
+        let original = Post::new(
+            "hello".to_string(),
+            rand_matter_with_lang(ja.clone()),
+            "body".to_string(),
+        );
+        let linked = Post::new(
+            "hola".to_string(),
+            rand_matter_with_lang(en.clone()).with_translation_of(Some(original.uuid())),
+            "body".to_string(),
+        );
+        let only_ja = Post::new(
+            "konnichiwa".to_string(),
+            rand_matter_with_lang(ja),
+            "body".to_string(),
+        );
+        for post in [&original, &linked, &only_ja] {
+            put(post, &index, &mut index_writer, false)?;
+        }
+
+        let uuids = |docs: Vec<Document>| -> Vec<String> {
+            docs.iter()
+                .map(|doc| fg.get_text(doc, PostField::Uuid).unwrap())
+                .collect()
+        };
+        let doc = get_by_uuid(&original.uuid(), &index)?;
+        assert_eq!(
+            uuids(get_translations(&doc, &index, visibility)?),
+            vec![linked.uuid()]
+        );
+        let doc = get_by_uuid(&linked.uuid(), &index)?;
+        assert_eq!(
+            uuids(get_translations(&doc, &index, visibility)?),
+            vec![original.uuid()]
+        );
+        let doc = get_by_uuid(&only_ja.uuid(), &index)?;
+        assert!(get_translations(&doc, &index, visibility)?.is_empty());
+
+        let (doc, fallback) = get_by_slug_with_fallback("hola", en.as_str(), &index, visibility)?;
+        assert_eq!(fg.get_text(&doc, PostField::Uuid)?, linked.uuid());
+        assert!(!fallback);
+        let (doc, fallback) =
+            get_by_slug_with_fallback("konnichiwa", en.as_str(), &index, visibility)?;
+        assert_eq!(fg.get_text(&doc, PostField::Uuid)?, only_ja.uuid());
+        assert!(fallback);
+        // the translation in the requested language is not a fallback
+        let (doc, fallback) = get_by_slug_with_fallback("hello", en.as_str(), &index, visibility)?;
+        assert_eq!(fg.get_text(&doc, PostField::Uuid)?, linked.uuid());
+        assert!(!fallback);
+        let (doc, fallback) = get_by_slug_with_fallback("hola", "ja", &index, visibility)?;
+        assert_eq!(fg.get_text(&doc, PostField::Uuid)?, original.uuid());
+        assert!(!fallback);
+        assert!(get_by_slug_with_fallback("missing", en.as_str(), &index, visibility).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_archive() -> Result<()> {
+        let temp_dir = TempDir::new(&format!("temp_rand_index_{}", uuid::Uuid::new_v4()))?;
+        let index = read_or_build_index(build_schema(), temp_dir.path(), true)?;
+        let schema = index.schema();
+        let fg = FieldGetter::new(&schema);
+        let mut index_writer = index.writer(100_000_000)?;
+        for created_at in ["2021-01-01", "2021-01-31", "2021-03-01", "2022-01-15"] {
+            put_post_with(
+                &index,
+                &mut index_writer,
+                "category",
+                rand_lang(),
+                Some(created_at),
+                Some(created_at),
+            )?;
+        }
+
+        let q: Box<dyn Query> = Box::new(AllQuery {});
+        let archive = get_archive(&*q, &index, false)?;
+        assert_eq!(
+            archive
+                .iter()
+                .map(|m| (m.year, m.month, m.count))
+                .collect::<Vec<_>>(),
+            vec![(2022, 1, 1), (2021, 3, 1), (2021, 1, 2)]
+        );
+        assert!(archive.iter().all(|m| m.docs.is_empty()));
+
+        let archive = get_archive(&*q, &index, true)?;
+        let dates: Vec<DateTime<Utc>> = archive[2]
+            .docs
+            .iter()
+            .map(|doc| fg.get_date(doc, PostField::CreatedAt).unwrap())
+            .collect();
+        assert_eq!(dates.len(), 2);
+        assert!(dates[0] > dates[1]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_adjacent() -> Result<()> {
+        let temp_dir = TempDir::new(&format!("temp_rand_index_{}", uuid::Uuid::new_v4()))?;
+        let index = read_or_build_index(build_schema(), temp_dir.path(), true)?;
+        let schema = index.schema();
+        let fg = FieldGetter::new(&schema);
+        let mut index_writer = index.writer(100_000_000)?;
+
+        let posts: Vec<Post> = [
+            ("en", "a"),
+            ("en", "b"),
+            ("en", "a"),
+            ("en", "a"),
+            ("ja", "a"),
+        ]
+        .iter()
+        .enumerate()
+        .map(|(i, (lang, category))| {
+            let created_at = format!("2021-01-0{}", i + 1);
+            put_post_with(
+                &index,
+                &mut index_writer,
+                category,
+                Lang::from_str(lang)?,
+                Some(&created_at),
+                Some(&created_at),
+            )
+        })
+        .collect::<Result<_>>()?;
+
+        let uuid = |doc: Option<Document>| doc.map(|d| fg.get_text(&d, PostField::Uuid).unwrap());
+        let term_query = |pf: PostField, text: &str| -> (Occur, Box<dyn Query>) {
+            let q: Box<dyn Query> = Box::new(TermQuery::new(
+                Term::from_field_text(fg.get_field(pf), text),
+                IndexRecordOption::Basic,
+            ));
+            (Occur::Must, q)
+        };
+
+        let doc = get_by_uuid(&posts[2].uuid(), &index)?;
+        let (prev, next) = get_adjacent(&doc, &[], &index)?;
+        assert_eq!(uuid(prev), Some(posts[1].uuid()));
+        assert_eq!(uuid(next), Some(posts[3].uuid()));
+
+        let filters = [
+            term_query(PostField::Lang, "en"),
+            term_query(PostField::Category, "a"),
+        ];
+        let (prev, next) = get_adjacent(&doc, &filters, &index)?;
+        assert_eq!(uuid(prev), Some(posts[0].uuid()));
+        assert_eq!(uuid(next), Some(posts[3].uuid()));
+
+        let doc = get_by_uuid(&posts[3].uuid(), &index)?;
+        let (_, next) = get_adjacent(&doc, &filters, &index)?;
+        assert!(next.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_adjacent_same_day() -> Result<()> {
+        let temp_dir = TempDir::new(&format!("temp_rand_index_{}", uuid::Uuid::new_v4()))?;
+        let index = read_or_build_index(build_schema(), temp_dir.path(), true)?;
+        let schema = index.schema();
+        let fg = FieldGetter::new(&schema);
+        let mut index_writer = index.writer(100_000_000)?;
+
+        let mut posts: Vec<Post> = [
+            "2021-01-01",
+            "2021-01-02",
+            "2021-01-02",
+            "2021-01-02",
+            "2021-01-03",
+        ]
+        .iter()
+        .map(|date| {
+            put_post_with(
+                &index,
+                &mut index_writer,
+                "a",
+                rand_lang(),
+                Some(date),
+                Some(date),
+            )
+        })
+        .collect::<Result<_>>()?;
+        // posts on the same day are ordered by uuid
+        posts[1..4].sort_by_key(|post| post.uuid());
+
+        let uuid = |doc: Option<Document>| doc.map(|d| fg.get_text(&d, PostField::Uuid).unwrap());
+        for i in 1..4 {
+            let doc = get_by_uuid(&posts[i].uuid(), &index)?;
+            let (prev, next) = get_adjacent(&doc, &[], &index)?;
+            assert_eq!(uuid(prev), Some(posts[i - 1].uuid()));
+            assert_eq!(uuid(next), Some(posts[i + 1].uuid()));
+        }
+        let doc = get_by_uuid(&posts[0].uuid(), &index)?;
+        let (prev, next) = get_adjacent(&doc, &[], &index)?;
+        assert!(prev.is_none());
+        assert_eq!(uuid(next), Some(posts[1].uuid()));
+        let doc = get_by_uuid(&posts[4].uuid(), &index)?;
+        let (prev, _) = get_adjacent(&doc, &[], &index)?;
+        assert_eq!(uuid(prev), Some(posts[3].uuid()));
+        Ok(())
+    }
+
     #[test]
     fn test_get_by_uuid() -> Result<()> {
         let temp_dir = TempDir::new(&format!(
@@ -320,10 +1498,36 @@ mod test {
 
         assert_eq!(tags, rand_tags);
         assert_eq!(categories, rand_categories);
+        assert_eq!(next_publish_at(&index)?, None);
 
         Ok(())
     }
 
+    #[test]
+    fn test_next_publish_at() -> Result<()> {
+        let temp_dir = TempDir::new(&format!("temp_rand_index_{}", uuid::Uuid::new_v4()))?;
+        let (_, index) = build_random_posts_index(3, temp_dir.path())?;
+        let mut index_writer = index.writer(100_000_000)?;
+        let now = Utc::now();
+        let at = |days: i64| {
+            DateTimeWithFormat::new(now + chrono::Duration::days(days), DateTimeFormat::RFC3339)
+        };
+        for (days, draft) in [(-1, false), (1, true), (2, false), (3, false)] {
+            let matter = rand_matter().with_publication(Publication {
+                draft,
+                publish_at: Some(at(days)),
+                unlisted: false,
+            });
+            let post = Post::new(rand_alpahbet(10), matter, "body".to_string());
+            put(&post, &index, &mut index_writer, true)?;
+        }
+
+        // drafts and published posts are ignored
+        let next = next_publish_at(&index)?.unwrap();
+        assert_eq!(next.timestamp(), at(2).datetime().timestamp());
+        Ok(())
+    }
+
     #[test]
     fn test_put() {
         let temp_dir = TempDir::new("test_put_query").unwrap();
@@ -343,6 +1547,28 @@ mod test {
         assert!(none.is_none());
     }
 
+    #[test]
+    fn test_stage_delete() -> Result<()> {
+        let temp_dir = TempDir::new(&format!("temp_rand_index_{}", uuid::Uuid::new_v4()))?;
+        let (posts, index) = build_random_posts_index(5, temp_dir.path())?;
+        let mut index_writer = index.writer(100_000_000)?;
+
+        let docs = get_all_by_uuid(&index)?;
+        assert_eq!(docs.len(), posts.len());
+
+        stage_delete(&posts[0].uuid(), &index.schema(), &index_writer);
+        stage_delete(&posts[1].uuid(), &index.schema(), &index_writer);
+        index_writer.commit()?;
+
+        let docs = get_all_by_uuid(&index)?;
+        assert_eq!(docs.len(), posts.len() - 2);
+        assert!(!docs.contains_key(&posts[0].uuid()));
+        assert!(!docs.contains_key(&posts[1].uuid()));
+        assert!(get_by_uuid(&posts[0].uuid(), &index).is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_put_with_format() -> Result<()> {
         use crate::test_utility::*;
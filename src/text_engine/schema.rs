@@ -1,12 +1,19 @@
-use crate::{datetime::DateTimeWithFormat, posts::Lang};
+use crate::{
+    config::{self, Config},
+    datetime::DateTimeWithFormat,
+    posts::{excerpt, Lang, TocEntry},
+};
 use anyhow::{anyhow, Result};
 use chrono::{DateTime, Utc};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 use tantivy::schema::*;
+use tantivy::Score;
 
 #[cfg(test)]
 use strum_macros::{EnumCount, EnumIter};
 
+use super::snippet::PostSnippets;
 use crate::datetime::DateTimeFormat;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -18,11 +25,19 @@ pub enum PostField {
     Description,
     Lang,
     Category,
+    CategoryFacet,
     Tags,
     Body,
     RawText,
+    Html,
+    Toc,
+    Extra,
+    TranslationOf,
+    Draft,
+    Unlisted,
     CreatedAt,
     UpdatedAt,
+    PublishAt,
     CreatedAtFormat,
     UpdatedAtFormat,
 }
@@ -36,17 +51,25 @@ impl PostField {
             PostField::Description => "description",
             PostField::Lang => "lang",
             PostField::Category => "category",
+            PostField::CategoryFacet => "category_facet",
             PostField::Tags => "tags",
             PostField::Body => "body",
             PostField::RawText => "raw_text",
+            PostField::Html => "html",
+            PostField::Toc => "toc",
+            PostField::Extra => "extra",
+            PostField::TranslationOf => "translation_of",
+            PostField::Draft => "draft",
+            PostField::Unlisted => "unlisted",
             PostField::CreatedAt => "created_at",
             PostField::UpdatedAt => "updated_at",
+            PostField::PublishAt => "publish_at",
             PostField::CreatedAtFormat => "created_at_format",
             PostField::UpdatedAtFormat => "updated_at_format",
         }
     }
 
-    pub fn text_fields() -> [Self; 10] {
+    pub fn text_fields() -> [Self; 17] {
         [
             PostField::Uuid,
             PostField::Slug,
@@ -56,32 +79,256 @@ impl PostField {
             PostField::Category,
             PostField::Tags,
             PostField::Body,
+            PostField::RawText,
+            PostField::Html,
+            PostField::Toc,
+            PostField::Extra,
+            PostField::TranslationOf,
+            PostField::Draft,
+            PostField::Unlisted,
             PostField::CreatedAtFormat,
             PostField::UpdatedAtFormat,
         ]
     }
 
-    pub fn date_fields() -> [Self; 2] {
-        [PostField::CreatedAt, PostField::UpdatedAt]
+    /// `PublishAt` is only in the posts scheduled by the frontmatter
+    pub fn date_fields() -> [Self; 3] {
+        [
+            PostField::CreatedAt,
+            PostField::UpdatedAt,
+            PostField::PublishAt,
+        ]
+    }
+
+    pub fn facet_fields() -> [Self; 1] {
+        [PostField::CategoryFacet]
+    }
+
+    /// fields indexed per language with the tokenizer of the language, such as `title_en`
+    pub fn lang_fields() -> [Self; 3] {
+        [PostField::Title, PostField::Description, PostField::RawText]
+    }
+
+    pub fn lang_field_name(&self, lang: &Lang) -> String {
+        format!("{}_{}", self.as_str(), lang.as_str())
+    }
+
+    /// fields emitted in `JSONDcument`
+    pub fn json_fields() -> [Self; 11] {
+        [
+            PostField::Uuid,
+            PostField::Slug,
+            PostField::Title,
+            PostField::Description,
+            PostField::Category,
+            PostField::Lang,
+            PostField::Tags,
+            PostField::Body,
+            PostField::CreatedAt,
+            PostField::UpdatedAt,
+            PostField::Extra,
+        ]
     }
+}
+
+impl FromStr for PostField {
+    type Err = anyhow::Error;
 
-    pub fn not_stored_fileds() -> [Self; 1] {
-        [PostField::RawText]
+    /// Field emitted in json, which is accepted in `fields`
+    fn from_str(field: &str) -> Result<Self> {
+        PostField::json_fields()
+            .into_iter()
+            .find(|pf| pf.as_str() == field)
+            .ok_or_else(|| anyhow!("{} is not a field of post", field))
+    }
+}
+
+pub const EXCERPT_LENGTH: usize = 200;
+
+/// Facet of category such as `/programming/rust` for `programming/rust`.
+/// Empty steps are ignored, so ` /programming//rust/ ` is the same facet.
+pub fn category_facet(category: &str) -> Facet {
+    Facet::from_path(
+        category
+            .split('/')
+            .map(|step| step.trim())
+            .filter(|step| !step.is_empty()),
+    )
+}
+
+/// Format of body emitted by `FieldGetter::to_json`.
+/// `html` is emitted in place of `body` with `Html`.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BodyFormat {
+    Markdown,
+    Html,
+}
+
+/// Fields of post emitted by `FieldGetter::to_json`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Projection {
+    fields: Vec<PostField>,
+    excerpt: bool,
+    body_format: BodyFormat,
+    toc: bool,
+    translations: bool,
+}
+
+impl Projection {
+    pub fn all() -> Self {
+        Self {
+            fields: PostField::json_fields().to_vec(),
+            excerpt: false,
+            body_format: BodyFormat::Markdown,
+            toc: false,
+            translations: false,
+        }
+    }
+
+    /// metadata of post without body, and excerpt from raw text of body
+    pub fn summary() -> Self {
+        Self {
+            fields: PostField::json_fields()
+                .into_iter()
+                .filter(|&pf| pf != PostField::Body)
+                .collect(),
+            excerpt: true,
+            body_format: BodyFormat::Markdown,
+            toc: false,
+            translations: false,
+        }
+    }
+
+    fn empty() -> Self {
+        Self {
+            fields: Vec::new(),
+            excerpt: false,
+            body_format: BodyFormat::Markdown,
+            toc: false,
+            translations: false,
+        }
+    }
+
+    /// Parse `summary` or comma separated field names such as `uuid,title,excerpt,toc,translations`.
+    pub fn parse(fields: &str) -> Result<Self> {
+        if fields == "summary" {
+            return Ok(Self::summary());
+        }
+
+        let mut projection = Self::empty();
+        for field in fields
+            .split(',')
+            .map(|f| f.trim())
+            .filter(|f| !f.is_empty())
+        {
+            if field == "excerpt" {
+                projection.excerpt = true;
+            } else if field == "toc" {
+                projection.toc = true;
+            } else if field == "translations" {
+                projection.translations = true;
+            } else {
+                projection.fields.push(field.parse()?);
+            }
+        }
+
+        if projection == Self::empty() {
+            return Err(anyhow!("fields is empty"));
+        }
+        Ok(projection)
+    }
+
+    pub fn from_query(fields: &Option<String>) -> Result<Self> {
+        match fields {
+            Some(fields) => Self::parse(fields),
+            None => Ok(Self::all()),
+        }
+    }
+
+    pub fn with_body_format(mut self, body_format: Option<BodyFormat>) -> Self {
+        self.body_format = body_format.unwrap_or(BodyFormat::Markdown);
+        self
+    }
+
+    /// Projection of the single post endpoints.
+    /// Table of contents and translations are also emitted if `fields` is not specified.
+    pub fn single(fields: &Option<String>, body_format: Option<BodyFormat>) -> Result<Self> {
+        let projection = match fields {
+            Some(fields) => Self::parse(fields)?,
+            None => Self {
+                toc: true,
+                translations: true,
+                ..Self::all()
+            },
+        };
+        Ok(projection.with_body_format(body_format))
+    }
+
+    /// Translations are looked up in the index, so they are set by `JSONDcument::set_translations`
+    pub fn translations(&self) -> bool {
+        self.translations
+    }
+}
+
+/// Post of another language linked as a translation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Translation {
+    pub lang: String,
+    pub uuid: String,
+    pub slug: String,
+}
+
+impl Translation {
+    pub fn from_doc(doc: &Document, fb: &FieldGetter) -> Result<Self> {
+        Ok(Self {
+            lang: fb.get_text(doc, PostField::Lang)?,
+            uuid: fb.get_text(doc, PostField::Uuid)?,
+            slug: fb.get_text(doc, PostField::Slug)?,
+        })
     }
 }
 
 #[derive(Debug, Serialize)]
 pub struct JSONDcument {
+    #[serde(skip_serializing_if = "Option::is_none")]
     uuid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     slug: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     category: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     lang: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     tags: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     body: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    html: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    toc: Option<Vec<TocEntry>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    translations: Option<Vec<Translation>>,
+    /// true if the post in the requested language is missing and the default language is returned
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lang_fallback: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    excerpt: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    snippets: Option<PostSnippets>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    score: Option<Score>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     updated_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     created_at: Option<String>,
+    /// frontmatter keys not used by smark
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extra: Option<serde_json::Map<String, serde_json::Value>>,
 }
 
 impl JSONDcument {
@@ -95,11 +342,35 @@ impl JSONDcument {
             lang: None,
             tags: None,
             body: None,
+            html: None,
+            toc: None,
+            translations: None,
+            lang_fallback: None,
+            excerpt: None,
+            snippets: None,
+            score: None,
             created_at: None,
             updated_at: None,
+            extra: None,
         }
     }
 
+    pub fn set_snippets(&mut self, snippets: PostSnippets) {
+        self.snippets = Some(snippets);
+    }
+
+    pub fn set_score(&mut self, score: Score) {
+        self.score = Some(score);
+    }
+
+    pub fn set_translations(&mut self, translations: Vec<Translation>) {
+        self.translations = Some(translations);
+    }
+
+    pub fn set_lang_fallback(&mut self) {
+        self.lang_fallback = Some(true);
+    }
+
     pub fn set(&mut self, doc: &Document, field: PostField, fb: &FieldGetter) -> Result<()> {
         match field {
             PostField::Uuid => {
@@ -142,6 +413,10 @@ impl JSONDcument {
                 let updated_at = fb.get_date_as_str(doc, field)?;
                 self.updated_at = Some(updated_at);
             }
+            PostField::Extra => {
+                let extra = fb.get_extra(doc)?;
+                self.extra = Some(extra);
+            }
             _ => {
                 return Ok(());
             }
@@ -168,15 +443,36 @@ impl<'a> FieldGetter<'a> {
             .unwrap_or_else(|| panic!("Error in PostField: {}", field_name))
     }
 
+    pub fn get_lang_field(&self, field: PostField, lang: &Lang) -> Field {
+        let field_name = field.lang_field_name(lang);
+
+        self.schema
+            .get_field(&field_name)
+            .unwrap_or_else(|| panic!("Error in PostField: {}", field_name))
+    }
+
+    /// language specific fields of `PostField::lang_fields` for `lang`, or for all languages if `None`
+    pub fn get_lang_fields(&self, lang: Option<&Lang>) -> Vec<Field> {
+        let langs = match lang {
+            Some(lang) => vec![lang.clone()],
+            None => Lang::all().to_vec(),
+        };
+        langs
+            .iter()
+            .flat_map(|lang| {
+                PostField::lang_fields()
+                    .into_iter()
+                    .map(move |pf| self.get_lang_field(pf, lang))
+            })
+            .collect()
+    }
+
     #[cfg(test)]
     pub fn get_fields(&self, fields: &[PostField]) -> Vec<Field> {
         fields.iter().map(|&pf| self.get_field(pf)).collect()
     }
 
     pub fn get_text(&self, doc: &Document, field: PostField) -> Result<String> {
-        if PostField::not_stored_fileds().contains(&field) {
-            return Err(anyhow!(format!("{} is not stored field", field.as_str())));
-        }
         if PostField::text_fields().contains(&field) {
             Ok(doc
                 .get_first(self.get_field(field))
@@ -190,9 +486,6 @@ impl<'a> FieldGetter<'a> {
     }
 
     pub fn get_date(&self, doc: &Document, field: PostField) -> Result<DateTime<Utc>> {
-        if PostField::not_stored_fileds().contains(&field) {
-            return Err(anyhow!(format!("{} is not stored field", field.as_str())));
-        }
         if PostField::date_fields().contains(&field) {
             Ok(doc
                 .get_first(self.get_field(field))
@@ -205,6 +498,13 @@ impl<'a> FieldGetter<'a> {
         }
     }
 
+    /// The date of the field which is not in every document, such as `PublishAt`
+    pub fn get_optional_date(&self, doc: &Document, field: PostField) -> Option<DateTime<Utc>> {
+        doc.get_first(self.get_field(field))
+            .and_then(|v| v.date_value())
+            .map(|date| date.to_owned())
+    }
+
     pub fn get_date_with_format(
         &self,
         doc: &Document,
@@ -233,6 +533,18 @@ impl<'a> FieldGetter<'a> {
         Ok(self.get_date_with_format(doc, field)?.to_string())
     }
 
+    /// Table of contents is stored as json
+    pub fn get_toc(&self, doc: &Document) -> Result<Vec<TocEntry>> {
+        Ok(serde_json::from_str(&self.get_text(doc, PostField::Toc)?)?)
+    }
+
+    /// Extra keys of frontmatter stored as json object
+    pub fn get_extra(&self, doc: &Document) -> Result<serde_json::Map<String, serde_json::Value>> {
+        Ok(serde_json::from_str(
+            &self.get_text(doc, PostField::Extra)?,
+        )?)
+    }
+
     pub fn get_tags(&self, doc: &Document) -> Result<Vec<String>> {
         let tag_str = self.get_text(doc, PostField::Tags)?;
         Ok(tag_str
@@ -243,15 +555,24 @@ impl<'a> FieldGetter<'a> {
             .collect())
     }
 
-    pub fn to_json(&self, doc: &Document) -> Result<JSONDcument> {
+    pub fn to_json(&self, doc: &Document, projection: &Projection) -> Result<JSONDcument> {
         let mut jd = JSONDcument::new();
 
-        for field in PostField::text_fields().into_iter() {
-            jd.set(doc, field, self)?;
+        for &field in projection.fields.iter() {
+            if field == PostField::Body && projection.body_format == BodyFormat::Html {
+                jd.html = Some(self.get_text(doc, PostField::Html)?);
+            } else {
+                jd.set(doc, field, self)?;
+            }
+        }
+
+        if projection.toc {
+            jd.toc = Some(self.get_toc(doc)?);
         }
 
-        for field in PostField::date_fields().into_iter() {
-            jd.set(doc, field, self)?;
+        if projection.excerpt {
+            let raw_text = self.get_text(doc, PostField::RawText)?;
+            jd.excerpt = Some(excerpt(&raw_text, EXCERPT_LENGTH));
         }
 
         Ok(jd)
@@ -292,10 +613,40 @@ impl SchemaConstructor {
         })
     }
 
+    pub fn build_stored_text_fields(&mut self, fields: &[PostField]) {
+        fields.iter().for_each(|field| {
+            self.schema_builder.add_text_field(field.as_str(), STORED);
+        })
+    }
+
+    /// Index `fields` as hierarchical facets, which match their descendants
+    pub fn build_facet_fields(&mut self, fields: &[PostField]) {
+        fields.iter().for_each(|field| {
+            self.schema_builder
+                .add_facet_field(field.as_str(), FacetOptions::default().set_indexed());
+        })
+    }
+
+    /// Index `fields` per language with the tokenizer of each language, without storing.
+    pub fn build_lang_fields(&mut self, fields: &[PostField], langs: &[Lang]) {
+        langs.iter().for_each(|lang| {
+            fields.iter().for_each(|field| {
+                self.schema_builder.add_text_field(
+                    &field.lang_field_name(lang),
+                    TextOptions::default().set_indexing_options(
+                        TextFieldIndexing::default()
+                            .set_tokenizer(&lang.tokenizer_name())
+                            .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+                    ),
+                );
+            })
+        })
+    }
+
     pub fn build_date_fields(&mut self, fields: &[PostField]) {
         fields.iter().for_each(|field| {
             self.schema_builder
-                .add_date_field(field.as_str(), FAST | STORED);
+                .add_date_field(field.as_str(), INDEXED | FAST | STORED);
         })
     }
 
@@ -317,26 +668,15 @@ impl SchemaConstructor {
             );
         })
     }
-
-    pub fn build_custom_tokenizer_text_field_no_stored(
-        &mut self,
-        tokenizer_name: &str,
-        fields: &[PostField],
-    ) {
-        fields.iter().for_each(|field| {
-            self.schema_builder.add_text_field(
-                field.as_str(),
-                TextOptions::default().set_indexing_options(
-                    TextFieldIndexing::default()
-                        .set_tokenizer(tokenizer_name)
-                        .set_index_option(IndexRecordOption::WithFreqsAndPositions),
-                ),
-            );
-        })
-    }
 }
 
+/// Schema for the configured languages
 pub fn build_schema() -> Schema {
+    build_schema_in(config::get())
+}
+
+/// Schema with the language specific fields of the languages of `config`
+pub fn build_schema_in(config: &Config) -> Schema {
     let mut constructor = SchemaConstructor::new();
 
     constructor.build_simple_text_fields(&[
@@ -354,17 +694,18 @@ pub fn build_schema() -> Schema {
             PostField::Slug,
             PostField::Category,
             PostField::Lang,
+            PostField::TranslationOf,
+            PostField::Draft,
+            PostField::Unlisted,
         ],
     );
-    constructor.build_custom_tokenizer_text_field(
-        &Lang::Ja.tokenizer_name(),
-        &[PostField::Title, PostField::Description],
-    );
-    constructor.build_custom_tokenizer_text_field_no_stored(
-        &Lang::Ja.tokenizer_name(),
-        &[PostField::RawText],
-    );
-    constructor.build_date_fields(&[PostField::CreatedAt, PostField::UpdatedAt]);
+    // raw text is stored as the source of snippets
+    constructor.build_stored_text_fields(&PostField::lang_fields());
+    // html and table of contents made at prep, and extra keys of frontmatter as json
+    constructor.build_stored_text_fields(&[PostField::Html, PostField::Toc, PostField::Extra]);
+    constructor.build_lang_fields(&PostField::lang_fields(), &Lang::all_in(config));
+    constructor.build_date_fields(&PostField::date_fields());
+    constructor.build_facet_fields(&PostField::facet_fields());
 
     constructor.schema_builder.build()
 }
@@ -384,14 +725,101 @@ mod test_textengine_schmea {
         }
     }
 
+    #[test]
+    fn test_build_schema_in() -> Result<()> {
+        let config = Config::from_yaml_str("languages:\n  ko: ngram\n  en: english\n")?;
+        let schema = build_schema_in(&config);
+        let has_lang_fields = |lang: &Lang| {
+            PostField::lang_fields()
+                .iter()
+                .all(|pf| schema.get_field(&pf.lang_field_name(lang)).is_some())
+        };
+        assert!(Lang::all_in(&config).iter().all(has_lang_fields));
+        assert!(!has_lang_fields(&Lang::from_str("ja")?));
+        Ok(())
+    }
+
     #[test]
     fn test_postfields_beloging_some_fields_getter() {
         assert_eq!(
             PostField::COUNT,
             PostField::text_fields().len()
                 + PostField::date_fields().len()
-                + PostField::not_stored_fileds().len()
+                + PostField::facet_fields().len()
+        )
+    }
+
+    #[test]
+    fn test_category_facet() {
+        assert_eq!(
+            category_facet("programming/rust"),
+            Facet::from("/programming/rust")
+        );
+        assert_eq!(
+            category_facet(" /programming//rust/ "),
+            Facet::from("/programming/rust")
+        );
+        assert!(category_facet("").is_root());
+    }
+
+    #[test]
+    fn test_projection() {
+        assert_eq!(Projection::parse("summary").unwrap(), Projection::summary());
+        assert_eq!(
+            Projection::parse("uuid, title,excerpt").unwrap(),
+            Projection {
+                fields: vec![PostField::Uuid, PostField::Title],
+                excerpt: true,
+                body_format: BodyFormat::Markdown,
+                toc: false,
+                translations: false,
+            }
+        );
+        assert_eq!(Projection::from_query(&None).unwrap(), Projection::all());
+        assert!(Projection::single(&None, None).unwrap().translations());
+        assert!(Projection::parse("title,translations")
+            .unwrap()
+            .translations());
+        assert!(Projection::parse("uuid,raw_text").is_err());
+        assert!(Projection::parse("unknown").is_err());
+        assert!(Projection::parse("").is_err());
+        assert!(Projection::parse(" , ").is_err());
+        assert_eq!("title".parse::<PostField>().unwrap(), PostField::Title);
+    }
+
+    #[test]
+    fn test_to_json_with_projection() {
+        let schema = build_schema();
+        let fg = FieldGetter::new(&schema);
+
+        let mut doc = Document::new();
+        PostField::text_fields()
+            .into_iter()
+            .filter(|&x| x != PostField::RawText && x != PostField::Extra)
+            .for_each(|x| doc.add_text(fg.get_field(x), "## TEST\n\nbody text"));
+        doc.add_text(fg.get_field(PostField::RawText), "TEST body text");
+        doc.add_text(
+            fg.get_field(PostField::Extra),
+            r#"{"thumbnail":"a.png","series":["rust"]}"#,
+        );
+        fg.get_fields(&PostField::date_fields())
+            .iter()
+            .for_each(|&x| doc.add_date(x, &Utc::now()));
+
+        let summary =
+            serde_json::to_value(fg.to_json(&doc, &Projection::summary()).unwrap()).unwrap();
+        assert!(summary.get("body").is_none());
+        assert!(summary.get("title").is_some());
+        assert_eq!(summary["excerpt"], "TEST body text");
+        assert_eq!(summary["extra"]["thumbnail"], "a.png");
+        assert_eq!(summary["extra"]["series"][0], "rust");
+
+        let only_title = serde_json::to_value(
+            fg.to_json(&doc, &Projection::parse("title").unwrap())
+                .unwrap(),
         )
+        .unwrap();
+        assert_eq!(only_title.as_object().unwrap().len(), 1);
     }
 
     #[test]
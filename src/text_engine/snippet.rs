@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use tantivy::{
+    query::Query,
+    schema::{Document, Schema},
+    Index, Snippet, SnippetGenerator,
+};
+
+use super::schema::{FieldGetter, PostField};
+use crate::posts::Lang;
+
+pub const DEFAULT_SNIPPET_LENGTH: usize = 150;
+pub const DEFAULT_HIGHLIGHT_PRE: &str = "<b>";
+pub const DEFAULT_HIGHLIGHT_POST: &str = "</b>";
+/// Tags allowed as the highlight markers given by users
+pub const HIGHLIGHT_TAGS: [&str; 3] = ["b", "em", "mark"];
+
+#[derive(Debug, Clone)]
+pub struct SnippetConfig {
+    pub max_num_chars: usize,
+    pub highlight_pre: String,
+    pub highlight_post: String,
+}
+
+impl SnippetConfig {
+    /// Config with the highlight markers given by users, e.g. in the query string.
+    /// The markers must be the opening and closing tags of one of `HIGHLIGHT_TAGS`,
+    /// since they are inserted into the snippets without escaping.
+    pub fn with_markers(
+        max_num_chars: usize,
+        highlight_pre: &str,
+        highlight_post: &str,
+    ) -> Result<Self> {
+        let valid = HIGHLIGHT_TAGS.iter().any(|tag| {
+            highlight_pre == format!("<{}>", tag) && highlight_post == format!("</{}>", tag)
+        });
+        if !valid {
+            return Err(anyhow!(
+                "highlight markers must be a pair of tags of {}, but got {} and {}",
+                HIGHLIGHT_TAGS.join(", "),
+                highlight_pre,
+                highlight_post
+            ));
+        }
+        Ok(Self {
+            max_num_chars,
+            highlight_pre: highlight_pre.to_string(),
+            highlight_post: highlight_post.to_string(),
+        })
+    }
+}
+
+impl Default for SnippetConfig {
+    fn default() -> Self {
+        Self {
+            max_num_chars: DEFAULT_SNIPPET_LENGTH,
+            highlight_pre: DEFAULT_HIGHLIGHT_PRE.to_string(),
+            highlight_post: DEFAULT_HIGHLIGHT_POST.to_string(),
+        }
+    }
+}
+
+/// Highlighted snippets of the post.
+/// A field is `None` if no term of the query is found in the field.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct PostSnippets {
+    title: Option<String>,
+    description: Option<String>,
+    body: Option<String>,
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Surround `ranges` of `text` with the highlight markers in the config.
+/// Text out of the markers is html escaped.
+/// Overlapping ranges, e.g. by ngram tokenizers, are merged into one.
+fn highlight(text: &str, ranges: &[Range<usize>], config: &SnippetConfig) -> String {
+    let mut sorted = ranges.to_vec();
+    sorted.sort_by_key(|range| range.start);
+    let mut merged: Vec<Range<usize>> = Vec::new();
+    for range in sorted.into_iter() {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+
+    let mut s = String::new();
+    let mut start_from = 0;
+    for range in merged.into_iter() {
+        s.push_str(&escape_html(&text[start_from..range.start]));
+        s.push_str(&config.highlight_pre);
+        s.push_str(&escape_html(&text[range.clone()]));
+        s.push_str(&config.highlight_post);
+        start_from = range.end;
+    }
+    s.push_str(&escape_html(&text[start_from..]));
+    s
+}
+
+fn render(snippet: &Snippet, config: &SnippetConfig) -> Option<String> {
+    if snippet.highlighted().is_empty() {
+        return None;
+    }
+    Some(highlight(
+        snippet.fragments(),
+        snippet.highlighted(),
+        config,
+    ))
+}
+
+/// Snippet generators of title, description and body for a language
+struct LangSnippetGenerators {
+    title: SnippetGenerator,
+    description: SnippetGenerator,
+    body: SnippetGenerator,
+}
+
+/// Generate snippets of title, description and body.
+/// Snippets are made with the tokenizer of the language of each post, e.g. `lang_en`.
+pub struct PostSnippetGenerator {
+    schema: Schema,
+    generators: HashMap<String, LangSnippetGenerators>,
+    config: SnippetConfig,
+}
+
+impl PostSnippetGenerator {
+    pub fn create(index: &Index, query: &dyn Query, config: SnippetConfig) -> Result<Self> {
+        let schema: Schema = index.schema();
+        let fb = FieldGetter::new(&schema);
+        let searcher = index.reader()?.searcher();
+
+        let generator = |pf: PostField, lang: &Lang| -> Result<SnippetGenerator> {
+            let mut generator =
+                SnippetGenerator::create(&searcher, query, fb.get_lang_field(pf, lang))?;
+            generator.set_max_num_chars(config.max_num_chars);
+            Ok(generator)
+        };
+
+        let generators = Lang::all()
+            .iter()
+            .map(|lang| {
+                let generators = LangSnippetGenerators {
+                    title: generator(PostField::Title, lang)?,
+                    description: generator(PostField::Description, lang)?,
+                    body: generator(PostField::RawText, lang)?,
+                };
+                Ok((lang.to_string(), generators))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            schema,
+            generators,
+            config,
+        })
+    }
+
+    pub fn snippets(&self, doc: &Document) -> Result<PostSnippets> {
+        let fb = FieldGetter::new(&self.schema);
+        let lang = fb.get_text(doc, PostField::Lang)?;
+        let generators = self
+            .generators
+            .get(&lang)
+            .ok_or_else(|| anyhow!("{} is not supported language", lang))?;
+
+        // language specific fields are not stored, so snippets are made from the stored text
+        let render_text = |generator: &SnippetGenerator, text: &str| {
+            render(&generator.snippet(text), &self.config)
+        };
+        Ok(PostSnippets {
+            title: render_text(&generators.title, &fb.get_text(doc, PostField::Title)?),
+            description: render_text(
+                &generators.description,
+                &fb.get_text(doc, PostField::Description)?,
+            ),
+            body: render_text(&generators.body, &fb.get_text(doc, PostField::RawText)?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utility::*;
+    use crate::text_engine::query::{get_by_uuid, parse_query, put};
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html("<a href=\"x\">&</a>"),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn test_highlight() {
+        let config = SnippetConfig::default();
+        assert_eq!(
+            highlight("a < b", &[4..5, 0..1], &config),
+            "<b>a</b> &lt; <b>b</b>"
+        );
+        // overlapping ngrams
+        let text = "한국어 본문";
+        assert_eq!(
+            highlight(text, &[10..13, 10..16, 13..16], &config),
+            "한국어 <b>본문</b>"
+        );
+    }
+
+    #[test]
+    fn test_snippets() -> Result<()> {
+        let temp_dir = TempDir::new("test_snippets")?;
+        let (_, index) = build_random_posts_index(3, temp_dir.path())?;
+        let post = rand_post_with_lang(
+            Lang::from_str("en")?,
+            "first paragraph\n\nsnippet keywords & more in body",
+        );
+        let mut index_writer = index.writer(100_000_000)?;
+        put(&post, &index, &mut index_writer, false)?;
+
+        let schema = index.schema();
+        let fb = FieldGetter::new(&schema);
+        let query = parse_query(
+            "keyword",
+            fb.get_lang_fields(Some(&Lang::from_str("en")?)),
+            &index,
+        )?;
+        let doc = get_by_uuid(&post.uuid(), &index)?;
+
+        let generator = PostSnippetGenerator::create(&index, &*query, SnippetConfig::default())?;
+        let snippets = generator.snippets(&doc)?;
+        assert_eq!(snippets.title, None);
+        assert!(snippets
+            .body
+            .as_ref()
+            .unwrap()
+            .contains("<b>keywords</b> &amp; more"));
+
+        let config = SnippetConfig {
+            max_num_chars: 20,
+            highlight_pre: "[[".to_string(),
+            highlight_post: "]]".to_string(),
+        };
+        let generator = PostSnippetGenerator::create(&index, &*query, config)?;
+        let body = generator.snippets(&doc)?.body.unwrap();
+        assert!(body.contains("[[keywords]]"));
+        assert!(!body.contains("first"));
+
+        assert!(SnippetConfig::with_markers(150, "<em>", "</em>").is_ok());
+        assert!(SnippetConfig::with_markers(150, "<em>", "</b>").is_err());
+        assert!(SnippetConfig::with_markers(150, "<img src=x onerror=alert(1)>", "</b>").is_err());
+
+        Ok(())
+    }
+}